@@ -0,0 +1,95 @@
+use crate::{query, segment, APIS};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "diesel", derive(diesel::Queryable))]
+pub struct Camera {
+    pub id: String,
+    pub display_name: Option<String>,
+    pub location: Option<String>,
+    /// Weight of a full feeder, in the same unit as `food_weight` of photos.
+    pub feeder_capacity: Option<i16>,
+    /// IANA name like `Asia/Shanghai`.
+    pub timezone: Option<String>,
+    /// Last time an upload came in, UTC.
+    pub last_seen: Option<NaiveDateTime>,
+    pub firmware: Option<String>,
+    /// Retired cameras are treated as unknown ones when uploading.
+    pub retired: bool,
+}
+
+/// Body of registering a camera.
+#[derive(Serialize, Deserialize, Default)]
+pub struct NewCamera {
+    pub id: String,
+    pub display_name: Option<String>,
+    pub location: Option<String>,
+    pub feeder_capacity: Option<i16>,
+    pub timezone: Option<String>,
+}
+
+/// The user editable part of a camera. Missing fields are cleared.
+#[derive(Serialize, Deserialize, Default)]
+pub struct CameraChanges {
+    pub display_name: Option<String>,
+    pub location: Option<String>,
+    pub feeder_capacity: Option<i16>,
+    pub timezone: Option<String>,
+}
+
+/// A camera with what its photos tell.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Overview {
+    #[serde(flatten)]
+    pub camera: Camera,
+    pub latest_photo: Option<String>,
+    /// When the camera last uploaded, UTC, which may be long after the
+    /// latest photo was taken.
+    pub last_upload: Option<NaiveDateTime>,
+    /// Food weight in the latest photo.
+    pub food_weight: Option<i16>,
+    pub photos: i64,
+}
+
+/// GET `Vec<Camera>`, or POST a `NewCamera`.
+pub fn list() -> String {
+    format!("{}/cameras", APIS)
+}
+
+/// GET `Vec<Overview>`.
+pub fn overview() -> String {
+    format!("{}/cameras/overview", APIS)
+}
+
+/// GET the `Camera`, PUT `CameraChanges` or DELETE it.
+pub fn camera(id: &str) -> String {
+    format!("{}/cameras/{}", APIS, segment(id))
+}
+
+/// POST, no `display_name` clears it.
+pub fn rename(id: &str, display_name: Option<&str>) -> String {
+    format!(
+        "{}/rename{}",
+        camera(id),
+        query(&[("display_name", display_name.map(str::to_string))])
+    )
+}
+
+/// POST, no `location` clears it.
+pub fn relocate(id: &str, location: Option<&str>) -> String {
+    format!(
+        "{}/relocate{}",
+        camera(id),
+        query(&[("location", location.map(str::to_string))])
+    )
+}
+
+/// POST.
+pub fn retire(id: &str, retired: bool) -> String {
+    format!(
+        "{}/retire{}",
+        camera(id),
+        query(&[("retired", Some(retired.to_string()))])
+    )
+}
@@ -0,0 +1,25 @@
+use crate::DB;
+use serde::{Deserialize, Serialize};
+
+/// What the database runs as, to check the settings took.
+#[derive(Serialize, Deserialize)]
+pub struct Status {
+    /// Latest applied migration, like `20261018160000`.
+    pub schema_version: Option<String>,
+    pub journal_mode: String,
+    /// 0 off, 1 normal, 2 full, 3 extra.
+    pub synchronous: i32,
+    pub foreign_keys: bool,
+    /// Milliseconds.
+    pub busy_timeout: i32,
+}
+
+/// POST to apply pending migrations, answers the `Status`.
+pub fn init() -> String {
+    format!("{}/init", DB)
+}
+
+/// GET the `Status`.
+pub fn status() -> String {
+    format!("{}/status", DB)
+}
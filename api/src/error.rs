@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What the server answers when a request fails, with a status telling the
+/// kind of failure.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorBody {
+    /// Like `not_found` or `conflict`, stable for clients to match on.
+    pub code: String,
+    pub message: String,
+    pub details: Option<String>,
+}
+
+impl fmt::Display for ErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.details {
+            Some(details) => write!(f, "{}: {}", self.message, details),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ErrorBody {}
@@ -0,0 +1,67 @@
+//! What the server, the client and the pages say to each other: bodies of
+//! requests and responses, and the paths they go to. Paths are relative to
+//! the server, like `/apis/names`, with parameters already percent-encoded.
+pub mod cameras;
+pub mod db;
+pub mod error;
+pub mod names;
+pub mod photos;
+pub mod stats;
+pub mod visits;
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+/// What every response body here is, for generic readers of them.
+pub use serde::de::DeserializeOwned;
+
+/// Where the server mounts its routes.
+pub const APIS: &str = "/apis";
+pub const DB: &str = "/db";
+pub const PICS: &str = "/pics";
+pub const THUMBS: &str = "/thumbs";
+
+/// https://url.spec.whatwg.org/#path-percent-encode-set, plus `/` and `%` for a single segment.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'<')
+    .add(b'>')
+    .add(b'`')
+    .add(b'#')
+    .add(b'?')
+    .add(b'{')
+    .add(b'}')
+    .add(b'/')
+    .add(b'%');
+/// https://url.spec.whatwg.org/#query-percent-encode-set, plus what separates parameters.
+const QUERY_VALUE: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'<')
+    .add(b'>')
+    .add(b'`')
+    .add(b'#')
+    .add(b'&')
+    .add(b'+')
+    .add(b'=')
+    .add(b'%');
+
+fn segment(value: &str) -> String {
+    utf8_percent_encode(value, PATH_SEGMENT).to_string()
+}
+
+/// `?key=value&...` of the parameters present, empty when none is.
+fn query(params: &[(&str, Option<String>)]) -> String {
+    let pairs = params
+        .iter()
+        .filter_map(|(key, value)| {
+            value
+                .as_ref()
+                .map(|v| format!("{}={}", key, utf8_percent_encode(v, QUERY_VALUE)))
+        })
+        .collect::<Vec<String>>();
+    if pairs.is_empty() {
+        "".to_string()
+    } else {
+        format!("?{}", pairs.join("&"))
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{query, segment, APIS};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+/// Every name, as answered by `names`.
+pub type Names = Vec<String>;
+
+/// Outcome of adding one name of a batch.
+#[derive(Serialize, Deserialize)]
+pub struct NewName {
+    pub name: String,
+    /// `None` when added.
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct NameCount {
+    pub name: String,
+    pub photos: i64,
+}
+
+/// Audit record of a rename or merge.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "diesel", derive(diesel::Queryable))]
+pub struct NameChange {
+    pub id: i32,
+    pub datetime: NaiveDateTime,
+    /// `rename` or `merge`.
+    pub action: String,
+    pub old_name: String,
+    pub new_name: String,
+    /// Photos moved from the old name to the new one.
+    pub photos: i32,
+}
+
+/// What is known about an animal beside its name.
+#[derive(Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    /// Filename of a photo.
+    pub headshot: Option<String>,
+    pub aliases: Vec<String>,
+    /// `male` or `female`.
+    pub sex: Option<String>,
+    /// Estimated.
+    pub birth_date: Option<NaiveDate>,
+    pub neutered: Option<bool>,
+    /// Colour and pattern.
+    pub description: Option<String>,
+    pub notes: Option<String>,
+    /// `active`, `deceased` or `adopted`.
+    pub status: String,
+    pub first_seen: Option<NaiveDateTime>,
+    pub last_seen: Option<NaiveDateTime>,
+}
+
+/// The editable part of a profile. Missing fields are cleared.
+#[derive(Serialize, Deserialize)]
+pub struct ProfileChanges {
+    pub headshot: Option<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    pub sex: Option<String>,
+    pub birth_date: Option<NaiveDate>,
+    pub neutered: Option<bool>,
+    pub description: Option<String>,
+    pub notes: Option<String>,
+    pub status: String,
+}
+
+impl From<&Profile> for ProfileChanges {
+    fn from(profile: &Profile) -> Self {
+        Self {
+            headshot: profile.headshot.clone(),
+            aliases: profile.aliases.clone(),
+            sex: profile.sex.clone(),
+            birth_date: profile.birth_date,
+            neutered: profile.neutered,
+            description: profile.description.clone(),
+            notes: profile.notes.clone(),
+            status: profile.status.clone(),
+        }
+    }
+}
+
+/// GET `Names`.
+pub fn names() -> String {
+    format!("{}/names", APIS)
+}
+
+/// POST comma separated `names`, answers a `NewName` for each.
+pub fn new_names(names: &str) -> String {
+    format!(
+        "{}/new_names{}",
+        APIS,
+        query(&[("names", Some(names.to_string()))])
+    )
+}
+
+/// GET `Vec<NameCount>`, alphabetical.
+pub fn counts() -> String {
+    format!("{}/names/counts", APIS)
+}
+
+/// GET `Vec<NameChange>`.
+pub fn changes() -> String {
+    format!("{}/names/changes", APIS)
+}
+
+/// POST.
+pub fn rename(from: &str, to: &str) -> String {
+    format!(
+        "{}/names/rename{}",
+        APIS,
+        query(&[("from", Some(from.to_string())), ("to", Some(to.to_string()))])
+    )
+}
+
+/// POST.
+pub fn merge(from: &str, into: &str) -> String {
+    format!(
+        "{}/names/merge{}",
+        APIS,
+        query(&[
+            ("from", Some(from.to_string())),
+            ("into", Some(into.to_string())),
+        ])
+    )
+}
+
+/// DELETE a name nothing carries.
+pub fn remove(name: &str) -> String {
+    format!("{}/names/{}", APIS, segment(name))
+}
+
+/// GET the `Profile`, or PUT `ProfileChanges`.
+pub fn profile(name: &str) -> String {
+    format!("{}/names/{}/profile", APIS, segment(name))
+}
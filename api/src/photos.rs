@@ -0,0 +1,218 @@
+use crate::{query, segment, APIS, PICS, THUMBS};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Photos by filename.
+pub type Filenames = Vec<String>;
+
+/// A guess of who is in a photo.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Identification {
+    pub name: String,
+    /// In `[0, 1]`, higher is more certain.
+    pub confidence: f32,
+}
+
+/// A photo with what is known about it.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "diesel", derive(diesel::Queryable))]
+pub struct Photo {
+    pub filename: String,
+    /// When it was taken, in UTC.
+    pub datetime: Option<NaiveDateTime>,
+    pub camera_id: String,
+    /// Weight of the food in the feeder when taken.
+    pub food_weight: i16,
+    pub name: Option<String>,
+    /// Of an automatic name, `None` when named by hand.
+    pub confidence: Option<f32>,
+    /// In pixels, `None` until measured.
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub visit_id: Option<i32>,
+}
+
+/// Which photos `list` answers, every one when left `Default`.
+#[derive(Clone, PartialEq, Default)]
+pub struct Filter {
+    pub camera_id: Option<String>,
+    pub name: Option<String>,
+    /// `Some(false)` for photos nobody is named in.
+    pub named: Option<bool>,
+    /// Taken at or after, in UTC.
+    pub since: Option<NaiveDateTime>,
+    /// Taken before, in UTC.
+    pub until: Option<NaiveDateTime>,
+    /// Of `food_weight`, both ends included.
+    pub min_weight: Option<i16>,
+    pub max_weight: Option<i16>,
+}
+
+/// By time taken, photos taken at the same time by filename.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Order {
+    NewestFirst,
+    OldestFirst,
+}
+
+impl fmt::Display for Order {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Order::NewestFirst => "newest",
+            Order::OldestFirst => "oldest",
+        })
+    }
+}
+
+impl FromStr for Order {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "newest" => Ok(Order::NewestFirst),
+            "oldest" => Ok(Order::OldestFirst),
+            _ => Err(format!("Unknown order {}, expecting newest or oldest", s)),
+        }
+    }
+}
+
+/// Some photos of a `list`.
+#[derive(Serialize, Deserialize)]
+pub struct Page {
+    pub photos: Vec<Photo>,
+    /// `after` of the page following this one, `None` on the last page.
+    pub next: Option<String>,
+}
+
+/// What `hash_photos` did.
+#[derive(Serialize, Deserialize)]
+pub struct Backfill {
+    /// Photos hashed and measured.
+    pub hashed: usize,
+    /// Photos with the same content as one stored before, left unhashed.
+    pub duplicates: Vec<Duplicate>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Duplicate {
+    pub filename: String,
+    /// The photo stored before.
+    pub of: String,
+}
+
+/// A photo already stored with the same content, answered with 409
+/// Conflict instead of storing an upload again.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "diesel", derive(diesel::Queryable))]
+pub struct Existing {
+    pub filename: String,
+    pub datetime: Option<NaiveDateTime>,
+    pub camera_id: String,
+    pub name: Option<String>,
+}
+
+/// POST the photo as the body. `taken_at` is seconds since the epoch, used
+/// when the photo has no EXIF time.
+pub fn new_image(
+    camera_id: &str,
+    food_weight: i16,
+    firmware: Option<&str>,
+    taken_at: Option<i64>,
+) -> String {
+    format!(
+        "{}/new_image{}",
+        APIS,
+        query(&[
+            ("camera_id", Some(camera_id.to_string())),
+            ("food_weight", Some(food_weight.to_string())),
+            ("firmware", firmware.map(str::to_string)),
+            ("taken_at", taken_at.map(|x| x.to_string())),
+        ])
+    )
+}
+
+/// GET groups of filenames that look the same, `Vec<Filenames>`.
+pub fn near_duplicates(max_distance: Option<u32>) -> String {
+    format!(
+        "{}/near_duplicates{}",
+        APIS,
+        query(&[("max_distance", max_distance.map(|x| x.to_string()))])
+    )
+}
+
+/// POST, answers a `Backfill`.
+pub fn hash_photos() -> String {
+    format!("{}/hash_photos", APIS)
+}
+
+/// GET a `Page` of the photos `filter` lets through. `after` is `next` of the
+/// previous page, `None` for the first one.
+pub fn list(filter: &Filter, order: Order, after: Option<&str>, limit: Option<u32>) -> String {
+    let datetime = |x: NaiveDateTime| x.format("%Y-%m-%dT%H:%M:%S").to_string();
+    format!(
+        "{}/photos{}",
+        APIS,
+        query(&[
+            ("camera_id", filter.camera_id.clone()),
+            ("name", filter.name.clone()),
+            ("named", filter.named.map(|x| x.to_string())),
+            ("since", filter.since.map(datetime)),
+            ("until", filter.until.map(datetime)),
+            ("min_weight", filter.min_weight.map(|x| x.to_string())),
+            ("max_weight", filter.max_weight.map(|x| x.to_string())),
+            ("order", Some(order.to_string())),
+            ("after", after.map(str::to_string)),
+            ("limit", limit.map(|x| x.to_string())),
+        ])
+    )
+}
+
+/// GET `Vec<Photo>` nobody is named in, oldest first.
+pub fn unnamed_images() -> String {
+    format!("{}/unnamed_images", APIS)
+}
+
+/// POST.
+pub fn name_image(filename: &str, name: &str) -> String {
+    format!(
+        "{}/name_image{}",
+        APIS,
+        query(&[
+            ("photo_filename", Some(filename.to_string())),
+            ("name", Some(name.to_string())),
+        ])
+    )
+}
+
+/// POST, the photo is unnamed again.
+pub fn unname_image(filename: &str) -> String {
+    format!(
+        "{}/unname_image{}",
+        APIS,
+        query(&[("photo_filename", Some(filename.to_string()))])
+    )
+}
+
+/// GET `Vec<Identification>`, best first.
+pub fn suggest_names(filename: &str, count: Option<usize>) -> String {
+    format!(
+        "{}/suggest_names{}",
+        APIS,
+        query(&[
+            ("photo_filename", Some(filename.to_string())),
+            ("count", count.map(|x| x.to_string())),
+        ])
+    )
+}
+
+/// The photo itself.
+pub fn pic(filename: &str) -> String {
+    format!("{}/{}", PICS, segment(filename))
+}
+
+/// The photo scaled to fit `size` pixels, a JPEG.
+pub fn thumbnail(size: u32, filename: &str) -> String {
+    format!("{}/{}/{}", THUMBS, size, segment(filename))
+}
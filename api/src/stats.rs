@@ -0,0 +1,97 @@
+use crate::{query, APIS};
+use chrono::{Datelike, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Length of the periods statistics are bucketed into.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Window {
+    Day,
+    Week,
+    Month,
+}
+
+impl Window {
+    /// Label of the period `datetime` falls in, sortable as text.
+    pub fn period(&self, datetime: NaiveDateTime) -> String {
+        match self {
+            Window::Day => datetime.format("%Y-%m-%d").to_string(),
+            Window::Week => {
+                let week = datetime.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            Window::Month => datetime.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Window::Day => "day",
+            Window::Week => "week",
+            Window::Month => "month",
+        })
+    }
+}
+
+impl FromStr for Window {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(Window::Day),
+            "week" => Ok(Window::Week),
+            "month" => Ok(Window::Month),
+            _ => Err(format!("Unknown window {}, expecting day, week or month", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Consumption {
+    /// Name or camera id, depending on the grouping.
+    pub key: String,
+    pub period: String,
+    pub consumed: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Count {
+    pub key: String,
+    pub period: String,
+    pub count: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HourlyActivity {
+    pub name: String,
+    /// Visits started in each hour of the day at the camera, index 0 is 00:00
+    /// to 00:59.
+    pub visits: [i64; 24],
+}
+
+fn window_query(window: Window) -> String {
+    query(&[("window", Some(window.to_string()))])
+}
+
+/// GET `Vec<Consumption>` by name.
+pub fn consumption_by_name(window: Window) -> String {
+    format!("{}/stats/consumption/names{}", APIS, window_query(window))
+}
+
+/// GET `Vec<Consumption>` by camera.
+pub fn consumption_by_camera(window: Window) -> String {
+    format!("{}/stats/consumption/cameras{}", APIS, window_query(window))
+}
+
+/// GET `Vec<Count>` of visits by name.
+pub fn visits_by_name(window: Window) -> String {
+    format!("{}/stats/visits{}", APIS, window_query(window))
+}
+
+/// GET `Vec<HourlyActivity>`.
+pub fn hourly_activity() -> String {
+    format!("{}/stats/hourly_activity", APIS)
+}
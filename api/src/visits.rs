@@ -0,0 +1,47 @@
+use crate::{query, APIS};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// One stay of an animal in front of a camera, made of consecutive photos.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "diesel", derive(diesel::Queryable))]
+pub struct Visit {
+    pub id: i32,
+    pub camera_id: String,
+    pub start_time: NaiveDateTime,
+    pub end_time: NaiveDateTime,
+    pub weight_before: i16,
+    pub weight_after: i16,
+    /// Never negative, a refill during the visit counts as nothing eaten.
+    pub consumed: i16,
+    pub name: Option<String>,
+}
+
+/// GET `Vec<Visit>`, latest first.
+pub fn list(camera_id: Option<&str>, unnamed: Option<bool>) -> String {
+    format!(
+        "{}/visits{}",
+        APIS,
+        query(&[
+            ("camera_id", camera_id.map(str::to_string)),
+            ("unnamed", unnamed.map(|x| x.to_string())),
+        ])
+    )
+}
+
+/// POST, names the visit and its photos.
+pub fn name(visit_id: i32, name: &str) -> String {
+    format!(
+        "{}/name_visit{}",
+        APIS,
+        query(&[
+            ("visit_id", Some(visit_id.to_string())),
+            ("name", Some(name.to_string())),
+        ])
+    )
+}
+
+/// POST, answers how many photos are put into visits.
+pub fn group() -> String {
+    format!("{}/group_visits", APIS)
+}
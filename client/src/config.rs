@@ -0,0 +1,148 @@
+use clap::Parser;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Upload feeder camera photos to a jian-ai server.
+///
+/// Flags override the config file, which overrides the defaults.
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// TOML file with the same keys as the long flags, in snake_case.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Server to upload to, as `host:port`. Default `localhost:8000`.
+    #[arg(long)]
+    host: Option<String>,
+    /// Talk HTTPS to the server.
+    #[arg(long, overrides_with = "no_tls")]
+    tls: bool,
+    /// Turn `tls` off, when the config file turns it on.
+    #[arg(long, overrides_with = "tls")]
+    no_tls: bool,
+    /// PEM file of an extra CA to trust, for self signed servers.
+    #[arg(long)]
+    ca_cert: Option<PathBuf>,
+    /// Accept any server certificate. Only for testing.
+    #[arg(long, overrides_with = "no_insecure")]
+    insecure: bool,
+    /// Turn `insecure` off, when the config file turns it on.
+    #[arg(long, overrides_with = "insecure")]
+    no_insecure: bool,
+    /// Camera the photos come from, as registered on the server, which
+    /// refuses photos of other cameras. Default `test`.
+    #[arg(long)]
+    camera_id: Option<String>,
+    /// Folder the camera writes photos into.
+    #[arg(long)]
+    folder: Option<PathBuf>,
+    /// Uploads in flight at the same time. Default 1.
+    #[arg(long)]
+    concurrency: Option<usize>,
+    /// Food weight sent along with every photo, without a scale, while it
+    /// has no reading, or for photos taken before the last 30 seconds.
+    /// Default 1.
+    #[arg(long)]
+    food_weight: Option<i16>,
+    /// Scale device printing a weight in grams per line, a serial port or a FIFO.
+    #[arg(long)]
+    scale: Option<PathBuf>,
+    /// After uploading what is in the folder, keep uploading new photos as they are written.
+    #[arg(long, overrides_with = "no_watch")]
+    watch: bool,
+    /// Turn `watch` off, when the config file turns it on.
+    #[arg(long, overrides_with = "watch")]
+    no_watch: bool,
+    /// Move uploaded photos into this folder, instead of marking them with an `.uploaded` suffix.
+    #[arg(long)]
+    move_to: Option<PathBuf>,
+    /// Journal of photos waiting to be uploaded. Default `.jian-ai-queue.json` in the folder.
+    #[arg(long)]
+    queue: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    host: Option<String>,
+    tls: Option<bool>,
+    ca_cert: Option<PathBuf>,
+    insecure: Option<bool>,
+    camera_id: Option<String>,
+    folder: Option<PathBuf>,
+    concurrency: Option<usize>,
+    food_weight: Option<i16>,
+    scale: Option<PathBuf>,
+    watch: Option<bool>,
+    move_to: Option<PathBuf>,
+    queue: Option<PathBuf>,
+}
+
+/// `--x` or `--no-x`, whichever was given last, else the config file, else off.
+fn flag(on: bool, off: bool, file: Option<bool>) -> bool {
+    match (on, off) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => file.unwrap_or(false),
+    }
+}
+
+pub struct Settings {
+    pub host: String,
+    pub tls: bool,
+    pub ca_cert: Option<PathBuf>,
+    pub insecure: bool,
+    pub camera_id: String,
+    pub folder: PathBuf,
+    pub concurrency: usize,
+    pub food_weight: i16,
+    pub scale: Option<PathBuf>,
+    pub watch: bool,
+    pub move_to: Option<PathBuf>,
+    pub queue: PathBuf,
+}
+
+impl Settings {
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let cli = Cli::parse();
+        let file: FileConfig = match &cli.config {
+            Some(path) => toml::from_str(&std::fs::read_to_string(path)?)?,
+            None => FileConfig::default(),
+        };
+        let folder = cli
+            .folder
+            .or(file.folder)
+            .ok_or("No folder given, by --folder or in the config file")?;
+        Ok(Settings {
+            host: cli
+                .host
+                .or(file.host)
+                .unwrap_or_else(|| "localhost:8000".to_string()),
+            tls: flag(cli.tls, cli.no_tls, file.tls),
+            ca_cert: cli.ca_cert.or(file.ca_cert),
+            insecure: flag(cli.insecure, cli.no_insecure, file.insecure),
+            camera_id: cli
+                .camera_id
+                .or(file.camera_id)
+                .unwrap_or_else(|| "test".to_string()),
+            concurrency: cli.concurrency.or(file.concurrency).unwrap_or(1).max(1),
+            food_weight: cli.food_weight.or(file.food_weight).unwrap_or(1),
+            scale: cli.scale.or(file.scale),
+            watch: flag(cli.watch, cli.no_watch, file.watch),
+            move_to: cli.move_to.or(file.move_to),
+            queue: cli
+                .queue
+                .or(file.queue)
+                .unwrap_or_else(|| folder.join(".jian-ai-queue.json")),
+            folder,
+        })
+    }
+
+    pub fn base_url(&self) -> String {
+        format!(
+            "{}://{}",
+            if self.tls { "https" } else { "http" },
+            self.host
+        )
+    }
+}
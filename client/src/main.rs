@@ -1,87 +1,109 @@
-#![feature(backtrace)]
-use futures::{stream, StreamExt, TryStreamExt};
-use reqwest::{Body, Client};
-use std::path::{Path, PathBuf};
-use tokio::fs::File;
-use tokio_util::codec::{BytesCodec, FramedRead};
+mod config;
+mod queue;
+mod scale;
+mod upload;
+mod watch;
+
+use config::Settings;
+use futures::future::join_all;
+use queue::Queue;
+use scale::{Fixed, LineScale, Scale, STALE_AFTER};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc::unbounded_channel;
+use upload::{is_photo, worth_retrying, Uploader};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let host = "localhost:8000".to_string();
-    let pic_folder = Path::new("/home/shida/pic/thumb/").to_path_buf();
-    let pics = pic_folder.read_dir()?;
-    let results = stream::iter(pics)
-        .map_ok(|pic| upload(pic.path(), host.clone()))
-        .map_err(|err| err.into())
-        .try_buffer_unordered(1);
-    results
-        .for_each(|result| async {
-            match result {
-                Ok(_) => {}
-                Err(e) => {
-                    println!("{}", e)
-                }
+    let settings = Settings::load()?;
+    let uploader = Uploader::new(&settings)?;
+    let mut queue = Queue::open(&settings.queue)?;
+    let scale: Box<dyn Scale> = match &settings.scale {
+        Some(device) => Box::new(LineScale::new(device.clone())),
+        None => Box::new(Fixed(settings.food_weight)),
+    };
+    // The scale only tells the weight now, which is the weight at capture
+    // for photos just written. Older ones, like those found at startup, get
+    // the fallback rather than a weight from after they were taken.
+    let weigh = |pic: &Path| {
+        let captured = queue::captured(pic);
+        let fresh = queue::now().saturating_sub(captured) <= STALE_AFTER.as_secs();
+        let weight = match scale.weight() {
+            Some(weight) if fresh => weight,
+            _ => {
+                println!(
+                    "No reading from the scale at {}, sending {}",
+                    pic.display(),
+                    settings.food_weight
+                );
+                settings.food_weight
             }
-        })
-        .await;
-
-    Ok(())
-}
-
-async fn upload(filename: PathBuf, host: String) -> Result<(), Box<dyn std::error::Error>> {
-    let file = File::open(filename).await?;
-    let stream = FramedRead::new(file, BytesCodec::new());
-    let body = Body::wrap_stream(stream);
+        };
+        (captured, weight)
+    };
 
-    let client = Client::new();
-    let response = client
-        .post(format!(
-            "http://{}/apis/new_image?camera_id=test&food_weight=1",
-            host
-        ))
-        .body(body)
-        .send()
-        .await?;
-
-    if response.status().is_success() {
-        Ok(())
+    // Watch before listing, so photos written in between are not missed.
+    let (tx, mut rx) = unbounded_channel();
+    let _watcher = if settings.watch {
+        Some(watch::watch(&settings.folder, tx)?)
     } else {
-        Err(ClientError::Upload(response.status(), response.text().await?).into())
+        drop(tx);
+        None
+    };
+    for pic in settings
+        .folder
+        .read_dir()?
+        .filter_map(|pic| pic.ok().map(|pic| pic.path()))
+        .filter(|pic| is_photo(pic))
+    {
+        let (captured, weight) = weigh(&pic);
+        queue.push(pic, captured, weight)?;
     }
-}
-
-use reqwest::StatusCode;
-use std::error::Error;
-use std::fmt::{Display, Formatter};
 
-#[derive(Debug)]
-enum ClientError {
-    Upload(StatusCode, String),
-}
-
-impl Display for ClientError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-        match &self {
-            ClientError::Upload(sc, _s) => {
-                write!(f, "Upload failed as {}", sc)
+    loop {
+        while let Ok(pic) = rx.try_recv() {
+            let (captured, weight) = weigh(&pic);
+            queue.push(pic, captured, weight)?;
+        }
+        let now = queue::now();
+        let due = queue.due(now, settings.concurrency);
+        if due.is_empty() {
+            // Without watching, what is not due yet waits for the next run.
+            if !settings.watch {
+                break;
+            }
+            let wait = queue
+                .next_attempt()
+                .map(|at| Duration::from_secs(at.saturating_sub(now).max(1)));
+            tokio::select! {
+                pic = rx.recv() => {
+                    let pic = pic.ok_or("Watching stopped")?;
+                    let (captured, weight) = weigh(&pic);
+                    queue.push(pic, captured, weight)?
+                }
+                _ = tokio::time::sleep(wait.unwrap_or(Duration::MAX)), if wait.is_some() => {}
             }
+            continue;
         }
-    }
-}
 
-impl Error for ClientError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        None
-    }
-    fn backtrace(&self) -> Option<&std::backtrace::Backtrace> {
-        None
-    }
-    fn description(&self) -> &str {
-        match &self {
-            ClientError::Upload(_sc, s) => s,
+        let results = join_all(due.iter().map(|pic| uploader.upload(pic))).await;
+        for (pic, result) in due.iter().map(|pic| &pic.path).zip(results) {
+            match result {
+                Ok(_) => queue.remove(pic)?,
+                Err(e) if worth_retrying(e.as_ref()) => {
+                    println!("{}: {}, will retry", pic.display(), e);
+                    queue.failed(pic, now)?
+                }
+                Err(e) => {
+                    println!("{}: {}, giving up", pic.display(), e);
+                    if let Err(e) = uploader.reject(pic).await {
+                        println!("Marking {} rejected failed: {}", pic.display(), e);
+                    }
+                    queue.remove(pic)?
+                }
+            }
         }
     }
-    fn cause(&self) -> Option<&dyn Error> {
-        None
-    }
+
+    Ok(())
 }
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// First retry waits this long, doubling on every failure after.
+const FIRST_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Pending {
+    pub path: PathBuf,
+    /// Seconds since the epoch, from the file's modified time.
+    pub captured: u64,
+    /// Grams on the scale when the photo was taken, as far as known.
+    pub food_weight: i16,
+    attempts: u32,
+    /// Seconds since the epoch.
+    next_attempt: u64,
+}
+
+/// Photos waiting to be uploaded, journaled to a JSON file on every change so
+/// a restart picks up where it stopped. Photos go out in capture order: one
+/// backing off holds back those captured after it.
+pub struct Queue {
+    file: PathBuf,
+    pending: Vec<Pending>,
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0)
+}
+
+/// Seconds since the epoch, from the file's modified time.
+pub fn captured(path: &Path) -> u64 {
+    std::fs::metadata(path)
+        .and_then(|x| x.modified())
+        .ok()
+        .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+        .map(|x| x.as_secs())
+        .unwrap_or_else(now)
+}
+
+impl Queue {
+    /// A missing file is an empty queue.
+    pub fn open(file: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let pending = match std::fs::read(file) {
+            Ok(json) => serde_json::from_slice(&json)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Queue {
+            file: file.to_path_buf(),
+            pending,
+        })
+    }
+
+    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        // Replace in one go, so a crash never leaves half a journal.
+        let tmp = self.file.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&self.pending)?)?;
+        std::fs::rename(&tmp, &self.file)?;
+        Ok(())
+    }
+
+    pub fn push(
+        &mut self,
+        path: PathBuf,
+        captured: u64,
+        food_weight: i16,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.pending.iter().any(|p| p.path == path) {
+            return Ok(());
+        }
+        let at = self.pending.partition_point(|p| p.captured <= captured);
+        self.pending.insert(
+            at,
+            Pending {
+                path,
+                captured,
+                food_weight,
+                attempts: 0,
+                next_attempt: 0,
+            },
+        );
+        self.save()
+    }
+
+    /// Up to `count` photos from the head of the queue that are due by `now`.
+    pub fn due(&self, now: u64, count: usize) -> Vec<Pending> {
+        self.pending
+            .iter()
+            .take_while(|p| p.next_attempt <= now)
+            .take(count)
+            .cloned()
+            .collect()
+    }
+
+    /// When the head of the queue is due, `None` if the queue is empty.
+    pub fn next_attempt(&self) -> Option<u64> {
+        self.pending.first().map(|p| p.next_attempt)
+    }
+
+    /// Uploaded, or given up on.
+    pub fn remove(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        self.pending.retain(|p| p.path != path);
+        self.save()
+    }
+
+    pub fn failed(&mut self, path: &Path, now: u64) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(p) = self.pending.iter_mut().find(|p| p.path == path) {
+            let backoff = FIRST_BACKOFF
+                .checked_mul(2u32.saturating_pow(p.attempts))
+                .unwrap_or(MAX_BACKOFF)
+                .min(MAX_BACKOFF);
+            p.attempts += 1;
+            p.next_attempt = now + backoff.as_secs();
+        }
+        self.save()
+    }
+}
@@ -0,0 +1,83 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Readings older than this are not trusted, the device has likely stopped.
+pub const STALE_AFTER: Duration = Duration::from_secs(30);
+
+pub trait Scale: Send + Sync {
+    /// Grams of food on the feeder right now, if known.
+    fn weight(&self) -> Option<i16>;
+}
+
+/// For feeders without a scale.
+pub struct Fixed(pub i16);
+
+impl Scale for Fixed {
+    fn weight(&self) -> Option<i16> {
+        Some(self.0)
+    }
+}
+
+/// A device printing one reading per line, like an HX711 behind a serial
+/// port (set up with `stty` beforehand), or a FIFO or file standing in for
+/// one. The first number on each line is the weight in grams, so `123.4`,
+/// `w=123` and `weight: 123 g` all read as 123.
+pub struct LineScale {
+    latest: Arc<Mutex<Option<(i16, Instant)>>>,
+}
+
+impl LineScale {
+    /// Keeps reading `device` on a background thread, reopening it when it
+    /// ends, as a FIFO does once its writer goes away.
+    pub fn new(device: PathBuf) -> Self {
+        let latest = Arc::new(Mutex::new(None));
+        let shared = latest.clone();
+        thread::spawn(move || loop {
+            match File::open(&device) {
+                Ok(file) => {
+                    for line in BufReader::new(file).lines() {
+                        match line {
+                            Ok(line) => {
+                                if let Some(weight) = parse(&line) {
+                                    *shared.lock().unwrap() = Some((weight, Instant::now()));
+                                }
+                            }
+                            Err(e) => {
+                                println!("Reading scale failed: {}", e);
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(e) => println!("Opening scale {} failed: {}", device.display(), e),
+            }
+            thread::sleep(Duration::from_secs(1));
+        });
+        LineScale { latest }
+    }
+}
+
+impl Scale for LineScale {
+    fn weight(&self) -> Option<i16> {
+        self.latest
+            .lock()
+            .unwrap()
+            .filter(|(_, at)| at.elapsed() < STALE_AFTER)
+            .map(|(weight, _)| weight)
+    }
+}
+
+fn parse(line: &str) -> Option<i16> {
+    let start = line.find(|c: char| c.is_ascii_digit() || c == '-')?;
+    let number = &line[start..];
+    let end = number[1..]
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .map(|x| x + 1)
+        .unwrap_or(number.len());
+    let grams: f32 = number[..end].parse().ok()?;
+    Some(grams.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16)
+}
@@ -0,0 +1,158 @@
+use crate::config::Settings;
+use crate::queue::Pending;
+use jian_ai_api::error::ErrorBody;
+use jian_ai_api::photos;
+use reqwest::{Body, Certificate, Client, StatusCode};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tokio_util::codec::{BytesCodec, FramedRead};
+
+/// Whether the server takes files like this one, by extension.
+pub fn is_photo(path: &Path) -> bool {
+    path.extension()
+        .and_then(|x| x.to_str())
+        .map(|x| {
+            ["jpg", "jpeg", "png", "webp"]
+                .iter()
+                .any(|e| x.eq_ignore_ascii_case(e))
+        })
+        .unwrap_or(false)
+}
+
+pub struct Uploader {
+    client: Client,
+    base_url: String,
+    camera_id: String,
+    move_to: Option<PathBuf>,
+}
+
+impl Uploader {
+    pub fn new(settings: &Settings) -> Result<Self, Box<dyn Error>> {
+        let mut builder = Client::builder();
+        if let Some(ca_cert) = &settings.ca_cert {
+            builder =
+                builder.add_root_certificate(Certificate::from_pem(&std::fs::read(ca_cert)?)?);
+        }
+        if settings.insecure {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        if let Some(move_to) = &settings.move_to {
+            std::fs::create_dir_all(move_to)?;
+        }
+        Ok(Uploader {
+            client: builder.build()?,
+            base_url: settings.base_url(),
+            camera_id: settings.camera_id.clone(),
+            move_to: settings.move_to.clone(),
+        })
+    }
+
+    /// Upload the photo, then move or mark it so it is not uploaded again.
+    pub async fn upload(&self, pic: &Pending) -> Result<(), Box<dyn Error>> {
+        let file = File::open(&pic.path).await?;
+        let stream = FramedRead::new(file, BytesCodec::new());
+        let body = Body::wrap_stream(stream);
+
+        let response = self
+            .client
+            .post(format!(
+                "{}{}",
+                self.base_url,
+                photos::new_image(
+                    &self.camera_id,
+                    pic.food_weight,
+                    None,
+                    Some(pic.captured as i64)
+                )
+            ))
+            .body(body)
+            .send()
+            .await?;
+
+        // A conflict is the same photo stored before, as good as uploaded.
+        if response.status().is_success() || response.status() == StatusCode::CONFLICT {
+            self.done(&pic.path).await?;
+            Ok(())
+        } else {
+            let status = response.status();
+            let text = response.text().await?;
+            let message = serde_json::from_str::<ErrorBody>(&text)
+                .map(|e| e.to_string())
+                .unwrap_or(text);
+            Err(ClientError::Upload(status, message).into())
+        }
+    }
+
+    async fn done(&self, filename: &Path) -> std::io::Result<()> {
+        let target = match (&self.move_to, filename.file_name()) {
+            (Some(move_to), Some(name)) => move_to.join(name),
+            _ => {
+                let mut marked = filename.as_os_str().to_owned();
+                marked.push(".uploaded");
+                PathBuf::from(marked)
+            }
+        };
+        if tokio::fs::rename(filename, &target).await.is_err() {
+            // Possibly across file systems.
+            tokio::fs::copy(filename, &target).await?;
+            tokio::fs::remove_file(filename).await?;
+        }
+        Ok(())
+    }
+
+    /// Mark a photo the server refused with a `.rejected` suffix, so it is
+    /// not listed again but stays around to look into.
+    pub async fn reject(&self, filename: &Path) -> std::io::Result<()> {
+        let mut marked = filename.as_os_str().to_owned();
+        marked.push(".rejected");
+        match tokio::fs::rename(filename, marked).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Network trouble and server errors may pass, a photo the server refuses or
+/// that is gone from disk will not get better.
+pub fn worth_retrying(e: &(dyn Error + 'static)) -> bool {
+    if let Some(ClientError::Upload(status, _)) = e.downcast_ref::<ClientError>() {
+        return !status.is_client_error()
+            || *status == StatusCode::REQUEST_TIMEOUT
+            || *status == StatusCode::TOO_MANY_REQUESTS;
+    }
+    if let Some(e) = e.downcast_ref::<std::io::Error>() {
+        return e.kind() != std::io::ErrorKind::NotFound;
+    }
+    true
+}
+
+#[derive(Debug)]
+enum ClientError {
+    Upload(StatusCode, String),
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match &self {
+            ClientError::Upload(sc, s) => {
+                write!(f, "Upload failed as {}: {}", sc, s)
+            }
+        }
+    }
+}
+
+impl Error for ClientError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+    fn description(&self) -> &str {
+        match &self {
+            ClientError::Upload(_sc, s) => s,
+        }
+    }
+    fn cause(&self) -> Option<&dyn Error> {
+        None
+    }
+}
@@ -0,0 +1,26 @@
+use crate::upload::is_photo;
+use notify::event::{AccessKind, AccessMode, ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Send every photo finished writing into, or moved into, `folder`.
+/// Watching stops when the returned watcher is dropped.
+pub fn watch(folder: &Path, tx: UnboundedSender<PathBuf>) -> notify::Result<RecommendedWatcher> {
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| match res {
+        Ok(event) => {
+            if matches!(
+                event.kind,
+                EventKind::Access(AccessKind::Close(AccessMode::Write))
+                    | EventKind::Modify(ModifyKind::Name(RenameMode::To))
+            ) {
+                for path in event.paths.into_iter().filter(|p| is_photo(p)) {
+                    let _ = tx.send(path);
+                }
+            }
+        }
+        Err(e) => println!("Watching failed: {}", e),
+    })?;
+    watcher.watch(folder, RecursiveMode::NonRecursive)?;
+    Ok(watcher)
+}
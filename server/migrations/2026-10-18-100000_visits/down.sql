@@ -0,0 +1,2 @@
+ALTER TABLE photos DROP COLUMN visit_id;
+DROP TABLE visits;
@@ -0,0 +1,13 @@
+CREATE TABLE visits (
+  id integer PRIMARY KEY AUTOINCREMENT NOT NULL,
+  camera_id text NOT NULL,
+  start_time timestamp NOT NULL,
+  end_time timestamp NOT NULL,
+  weight_before smallint NOT NULL,
+  weight_after smallint NOT NULL,
+  consumed smallint NOT NULL,
+  name text,
+  FOREIGN KEY(name) REFERENCES names(name)
+);
+
+ALTER TABLE photos ADD COLUMN visit_id integer;
@@ -0,0 +1,12 @@
+CREATE TABLE cameras (
+  id text PRIMARY KEY NOT NULL,
+  display_name text,
+  location text,
+  feeder_capacity smallint,
+  timezone text,
+  last_seen timestamp,
+  firmware text
+);
+
+-- cameras that already uploaded are known ones
+INSERT INTO cameras (id) SELECT DISTINCT camera_id FROM photos;
@@ -0,0 +1 @@
+ALTER TABLE cameras ADD COLUMN retired boolean NOT NULL DEFAULT 0;
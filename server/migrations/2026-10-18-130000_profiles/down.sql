@@ -0,0 +1,8 @@
+ALTER TABLE names DROP COLUMN status;
+ALTER TABLE names DROP COLUMN notes;
+ALTER TABLE names DROP COLUMN description;
+ALTER TABLE names DROP COLUMN neutered;
+ALTER TABLE names DROP COLUMN birth_date;
+ALTER TABLE names DROP COLUMN sex;
+ALTER TABLE names DROP COLUMN aliases;
+ALTER TABLE names DROP COLUMN headshot;
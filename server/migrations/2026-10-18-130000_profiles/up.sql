@@ -0,0 +1,9 @@
+ALTER TABLE names ADD COLUMN headshot text;
+-- comma separated
+ALTER TABLE names ADD COLUMN aliases text;
+ALTER TABLE names ADD COLUMN sex text CHECK (sex IN ('male', 'female'));
+ALTER TABLE names ADD COLUMN birth_date date;
+ALTER TABLE names ADD COLUMN neutered boolean;
+ALTER TABLE names ADD COLUMN description text;
+ALTER TABLE names ADD COLUMN notes text;
+ALTER TABLE names ADD COLUMN status text NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'deceased', 'adopted'));
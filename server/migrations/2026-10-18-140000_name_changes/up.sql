@@ -0,0 +1,9 @@
+CREATE TABLE name_changes (
+  id integer PRIMARY KEY AUTOINCREMENT NOT NULL,
+  datetime timestamp NOT NULL DEFAULT (datetime('now')),
+  action text NOT NULL CHECK (action IN ('rename', 'merge')),
+  old_name text NOT NULL,
+  new_name text NOT NULL,
+  -- photos moved from old_name to new_name
+  photos integer NOT NULL
+);
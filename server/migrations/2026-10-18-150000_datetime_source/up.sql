@@ -0,0 +1,2 @@
+-- exif, client or server, unknown for photos from before
+ALTER TABLE photos ADD COLUMN datetime_source text CHECK (datetime_source IN ('exif', 'client', 'server'));
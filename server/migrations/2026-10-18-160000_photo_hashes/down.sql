@@ -0,0 +1,3 @@
+DROP INDEX photos_content_hash;
+ALTER TABLE photos DROP COLUMN perceptual_hash;
+ALTER TABLE photos DROP COLUMN content_hash;
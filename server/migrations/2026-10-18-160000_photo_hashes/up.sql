@@ -0,0 +1,5 @@
+-- hex sha256 of the file
+ALTER TABLE photos ADD COLUMN content_hash text;
+-- dHash, 64 bits
+ALTER TABLE photos ADD COLUMN perceptual_hash bigint;
+CREATE UNIQUE INDEX photos_content_hash ON photos (content_hash);
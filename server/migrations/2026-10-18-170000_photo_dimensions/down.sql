@@ -0,0 +1,2 @@
+ALTER TABLE photos DROP COLUMN height;
+ALTER TABLE photos DROP COLUMN width;
@@ -0,0 +1,3 @@
+-- pixels, of the photo as uploaded
+ALTER TABLE photos ADD COLUMN width integer;
+ALTER TABLE photos ADD COLUMN height integer;
@@ -0,0 +1 @@
+DROP INDEX photos_datetime;
@@ -0,0 +1,2 @@
+-- pages of /apis/photos walk photos in this order
+CREATE INDEX photos_datetime ON photos (datetime, filename);
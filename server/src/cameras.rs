@@ -0,0 +1,113 @@
+use crate::schema::{cameras, photos};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::{delete, insert_into, update};
+pub use jian_ai_api::cameras::{Camera, CameraChanges, NewCamera, Overview};
+
+pub fn list(conn: &SqliteConnection) -> QueryResult<Vec<Camera>> {
+    cameras::table.order(cameras::id).load(conn)
+}
+
+pub fn get(conn: &SqliteConnection, id: &str) -> QueryResult<Option<Camera>> {
+    cameras::table.find(id).first(conn).optional()
+}
+
+pub fn create(conn: &SqliteConnection, camera: &NewCamera) -> QueryResult<()> {
+    insert_into(cameras::table)
+        .values((
+            cameras::id.eq(&camera.id),
+            cameras::display_name.eq(&camera.display_name),
+            cameras::location.eq(&camera.location),
+            cameras::feeder_capacity.eq(camera.feeder_capacity),
+            cameras::timezone.eq(&camera.timezone),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Returns false when there is no such camera. Missing fields are cleared.
+pub fn change(conn: &SqliteConnection, id: &str, changes: &CameraChanges) -> QueryResult<bool> {
+    Ok(update(cameras::table.find(id))
+        .set((
+            cameras::display_name.eq(&changes.display_name),
+            cameras::location.eq(&changes.location),
+            cameras::feeder_capacity.eq(changes.feeder_capacity),
+            cameras::timezone.eq(&changes.timezone),
+        ))
+        .execute(conn)?
+        > 0)
+}
+
+/// Returns false when there is no such camera.
+pub fn remove(conn: &SqliteConnection, id: &str) -> QueryResult<bool> {
+    Ok(delete(cameras::table.find(id)).execute(conn)? > 0)
+}
+
+/// Returns false when there is no such camera.
+pub fn rename(conn: &SqliteConnection, id: &str, display_name: Option<&str>) -> QueryResult<bool> {
+    Ok(update(cameras::table.find(id))
+        .set(cameras::display_name.eq(display_name))
+        .execute(conn)?
+        > 0)
+}
+
+/// Returns false when there is no such camera.
+pub fn relocate(conn: &SqliteConnection, id: &str, location: Option<&str>) -> QueryResult<bool> {
+    Ok(update(cameras::table.find(id))
+        .set(cameras::location.eq(location))
+        .execute(conn)?
+        > 0)
+}
+
+/// Returns false when there is no such camera.
+pub fn retire(conn: &SqliteConnection, id: &str, retired: bool) -> QueryResult<bool> {
+    Ok(update(cameras::table.find(id))
+        .set(cameras::retired.eq(retired))
+        .execute(conn)?
+        > 0)
+}
+
+/// Record that the camera is alive, with the firmware it reports if any.
+/// Returns false when the camera is not registered or retired.
+pub fn heartbeat(conn: &SqliteConnection, id: &str, firmware: Option<&str>) -> QueryResult<bool> {
+    let now = Utc::now().naive_utc();
+    let camera = cameras::table.find(id).filter(cameras::retired.eq(false));
+    let updated = match firmware {
+        Some(firmware) => update(camera)
+            .set((
+                cameras::last_seen.eq(Some(now)),
+                cameras::firmware.eq(Some(firmware)),
+            ))
+            .execute(conn)?,
+        None => update(camera)
+            .set(cameras::last_seen.eq(Some(now)))
+            .execute(conn)?,
+    };
+    Ok(updated > 0)
+}
+
+pub fn overview(conn: &SqliteConnection) -> QueryResult<Vec<Overview>> {
+    list(conn)?
+        .into_iter()
+        .map(|camera| {
+            let photos = photos::table
+                .filter(photos::camera_id.eq(&camera.id))
+                .count()
+                .get_result(conn)?;
+            let latest: Option<(String, i16)> = photos::table
+                .select((photos::filename, photos::food_weight))
+                .filter(photos::camera_id.eq(&camera.id))
+                .order(photos::datetime.desc())
+                .first(conn)
+                .optional()?;
+            let (latest_photo, food_weight) = latest.unzip();
+            Ok(Overview {
+                last_upload: camera.last_seen,
+                camera,
+                latest_photo,
+                food_weight,
+                photos,
+            })
+        })
+        .collect()
+}
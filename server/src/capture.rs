@@ -0,0 +1,78 @@
+use chrono::{FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use exif::{In, Reader, Tag, Value};
+use std::io::Cursor;
+
+/// Where `photos.datetime` came from, stored in `photos.datetime_source`.
+#[derive(Clone, Copy)]
+pub enum Source {
+    /// `DateTimeOriginal` of the photo.
+    Exif,
+    /// `taken_at` sent by the uploader.
+    Client,
+    /// Arrival at the server.
+    Server,
+}
+
+impl Source {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Source::Exif => "exif",
+            Source::Client => "client",
+            Source::Server => "server",
+        }
+    }
+}
+
+/// When the `photo` was taken, UTC, best source first.
+///
+/// EXIF times are local to the camera. They are converted by the offset
+/// recorded beside them, else by `timezone` of the camera, else taken as UTC.
+pub fn taken_at(
+    photo: &[u8],
+    timezone: Option<&str>,
+    client: Option<NaiveDateTime>,
+) -> (NaiveDateTime, Source) {
+    if let Some(datetime) = from_exif(photo, timezone) {
+        (datetime, Source::Exif)
+    } else if let Some(datetime) = client {
+        (datetime, Source::Client)
+    } else {
+        (Utc::now().naive_utc(), Source::Server)
+    }
+}
+
+fn ascii(exif: &exif::Exif, tag: Tag) -> Option<Vec<u8>> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(x) => x.first().cloned(),
+        _ => None,
+    }
+}
+
+fn from_exif(photo: &[u8], timezone: Option<&str>) -> Option<NaiveDateTime> {
+    let exif = Reader::new()
+        .read_from_container(&mut Cursor::new(photo))
+        .ok()?;
+    let mut original = exif::DateTime::from_ascii(&ascii(&exif, Tag::DateTimeOriginal)?).ok()?;
+    let local = NaiveDate::from_ymd_opt(
+        original.year as i32,
+        original.month as u32,
+        original.day as u32,
+    )?
+    .and_hms_opt(
+        original.hour as u32,
+        original.minute as u32,
+        original.second as u32,
+    )?;
+    if let Some(offset) = ascii(&exif, Tag::OffsetTimeOriginal) {
+        if original.parse_offset(&offset).is_ok() {
+            let offset = FixedOffset::east_opt(original.offset? as i32 * 60)?;
+            return Some(offset.from_local_datetime(&local).single()?.naive_utc());
+        }
+    }
+    match timezone.and_then(|x| x.parse::<Tz>().ok()) {
+        // An ambiguous time at the end of DST is taken as the earlier one.
+        Some(tz) => Some(tz.from_local_datetime(&local).earliest()?.naive_utc()),
+        None => Some(local),
+    }
+}
@@ -0,0 +1,63 @@
+use diesel::connection::SimpleConnection;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, Pool};
+use diesel::sql_types::{Integer, Nullable, Text};
+pub use jian_ai_api::db::Status;
+
+pub type DbPool = Pool<ConnectionManager<SqliteConnection>>;
+
+/// SQLite settings are per connection, so they are applied to each one the
+/// pool opens.
+#[derive(Debug)]
+struct Pragmas {
+    busy_timeout_ms: u32,
+}
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for Pragmas {
+    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+        conn.batch_execute(&format!(
+            "PRAGMA journal_mode = WAL; \
+             PRAGMA synchronous = NORMAL; \
+             PRAGMA foreign_keys = ON; \
+             PRAGMA busy_timeout = {};",
+            self.busy_timeout_ms
+        ))
+        .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+pub fn pool(url: &str, size: u32, busy_timeout_ms: u32) -> Result<DbPool, r2d2::Error> {
+    Pool::builder()
+        .max_size(size)
+        .connection_customizer(Box::new(Pragmas { busy_timeout_ms }))
+        .build(ConnectionManager::new(url))
+}
+
+pub fn status(conn: &SqliteConnection) -> QueryResult<Status> {
+    Ok(Status {
+        schema_version: sql::<Nullable<Text>>(
+            "SELECT max(version) FROM __diesel_schema_migrations",
+        )
+        .get_result(conn)?,
+        journal_mode: sql::<Text>("SELECT * FROM pragma_journal_mode").get_result(conn)?,
+        synchronous: sql::<Integer>("SELECT * FROM pragma_synchronous").get_result(conn)?,
+        foreign_keys: sql::<Integer>("SELECT * FROM pragma_foreign_keys")
+            .get_result::<i32>(conn)?
+            == 1,
+        busy_timeout: sql::<Integer>("SELECT * FROM pragma_busy_timeout").get_result(conn)?,
+    })
+}
+
+#[cfg(test)]
+embed_migrations!();
+
+/// A fresh migrated database for tests, gone when dropped.
+#[cfg(test)]
+pub fn memory() -> SqliteConnection {
+    let conn = SqliteConnection::establish(":memory:").expect("Opening SQLite in memory");
+    conn.batch_execute("PRAGMA foreign_keys = ON;")
+        .expect("Turning on foreign keys");
+    embedded_migrations::run(&conn).expect("Migrating");
+    conn
+}
@@ -0,0 +1,183 @@
+use crate::schema::photos;
+use crate::storage::PhotoStore;
+use diesel::prelude::*;
+use diesel::update;
+use image::imageops::FilterType;
+use image::DynamicImage;
+pub use jian_ai_api::photos::{Backfill, Duplicate, Existing};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Hex SHA-256, equal only for byte identical photos.
+pub fn content_hash(photo: &[u8]) -> String {
+    Sha256::digest(photo)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// dHash: whether each pixel of a 9x8 grayscale thumbnail is brighter than
+/// its right neighbour. Re-encoded or slightly shifted shots differ in a few
+/// bits only.
+pub fn perceptual_hash(img: &DynamicImage) -> i64 {
+    let thumb = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if thumb.get_pixel(x, y).0[0] > thumb.get_pixel(x + 1, y).0[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash as i64
+}
+
+pub fn find(conn: &SqliteConnection, content_hash: &str) -> QueryResult<Option<Existing>> {
+    photos::table
+        .select((
+            photos::filename,
+            photos::datetime,
+            photos::camera_id,
+            photos::name,
+        ))
+        .filter(photos::content_hash.eq(content_hash))
+        .first(conn)
+        .optional()
+}
+
+/// Hash and measure photos stored before that was done. Photos that cannot
+/// be read are skipped. Photos with the same content as another are reported
+/// and left unhashed, as content hashes are unique.
+pub fn backfill(
+    conn: &SqliteConnection,
+    store: &dyn PhotoStore,
+) -> Result<Backfill, Box<dyn std::error::Error>> {
+    let missing: Vec<String> = photos::table
+        .select(photos::filename)
+        .filter(photos::content_hash.is_null().or(photos::width.is_null()))
+        .load(conn)?;
+    let mut done = Backfill {
+        hashed: 0,
+        duplicates: vec![],
+    };
+    for filename in missing {
+        let photo = match store.get(&filename) {
+            Ok(Some(x)) => x,
+            Ok(None) => {
+                eprintln!("Hashing {} failed: photo is missing", filename);
+                continue;
+            }
+            Err(e) => {
+                eprintln!("Hashing {} failed: {}", filename, e);
+                continue;
+            }
+        };
+        let hash = content_hash(&photo);
+        let of: Option<String> = photos::table
+            .select(photos::filename)
+            .filter(photos::content_hash.eq(&hash))
+            .filter(photos::filename.ne(&filename))
+            .first(conn)
+            .optional()?;
+        if let Some(of) = of {
+            eprintln!("{} is a duplicate of {}", filename, of);
+            done.duplicates.push(Duplicate { filename, of });
+            continue;
+        }
+        let img = image::load_from_memory(&photo).ok();
+        update(photos::table.find(&filename))
+            .set((
+                photos::content_hash.eq(hash),
+                photos::perceptual_hash.eq(img.as_ref().map(perceptual_hash)),
+                photos::width.eq(img.as_ref().map(|x| x.width() as i32)),
+                photos::height.eq(img.as_ref().map(|x| x.height() as i32)),
+            ))
+            .execute(conn)?;
+        done.hashed += 1;
+    }
+    Ok(done)
+}
+
+/// Groups of photos whose perceptual hashes are at most `max_distance` bits
+/// apart, transitively. Every pair is compared, fine for a feeder's worth of
+/// photos.
+pub fn near_duplicates(
+    conn: &SqliteConnection,
+    max_distance: u32,
+) -> QueryResult<Vec<Vec<String>>> {
+    let hashes: Vec<(String, Option<i64>)> = photos::table
+        .select((photos::filename, photos::perceptual_hash))
+        .filter(photos::perceptual_hash.is_not_null())
+        .order(photos::datetime)
+        .load(conn)?;
+    let hashes: Vec<(String, u64)> = hashes
+        .into_iter()
+        .filter_map(|(filename, hash)| hash.map(|x| (filename, x as u64)))
+        .collect();
+
+    // Union find over indexes.
+    let mut parent: Vec<usize> = (0..hashes.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..hashes.len() {
+        for j in i + 1..hashes.len() {
+            if (hashes[i].1 ^ hashes[j].1).count_ones() <= max_distance {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[b] = a;
+            }
+        }
+    }
+
+    let mut clusters: HashMap<usize, Vec<String>> = HashMap::new();
+    for (i, (filename, _)) in hashes.iter().enumerate() {
+        let r = root(&mut parent, i);
+        clusters.entry(r).or_default().push(filename.clone());
+    }
+    let mut clusters: Vec<Vec<String>> = clusters.into_values().filter(|c| c.len() > 1).collect();
+    clusters.sort();
+    Ok(clusters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::LocalStore;
+    use diesel::insert_into;
+
+    #[test]
+    fn backfill_reports_duplicates_instead_of_failing() {
+        let conn = crate::db::memory();
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path().to_path_buf(), false).unwrap();
+        for (key, bytes) in [("a", b"same"), ("b", b"same"), ("c", b"else")] {
+            store.put(key, bytes).unwrap();
+        }
+        for filename in ["a", "b", "c"] {
+            insert_into(photos::table)
+                .values((
+                    photos::filename.eq(filename),
+                    photos::camera_id.eq("cam"),
+                    photos::food_weight.eq(0),
+                ))
+                .execute(&conn)
+                .unwrap();
+        }
+        let done = backfill(&conn, &store).unwrap();
+        assert_eq!(done.hashed, 2);
+        let duplicates: Vec<(&str, &str)> = done
+            .duplicates
+            .iter()
+            .map(|x| (x.filename.as_str(), x.of.as_str()))
+            .collect();
+        assert_eq!(duplicates, vec![("b", "a")]);
+        // Reruns report the same instead of failing.
+        let done = backfill(&conn, &store).unwrap();
+        assert_eq!(done.duplicates.len(), 1);
+    }
+}
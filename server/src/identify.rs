@@ -0,0 +1,197 @@
+use crate::schema::photos;
+use crate::storage::PhotoStore;
+use diesel::prelude::*;
+use image::imageops::FilterType;
+pub use jian_ai_api::photos::Identification;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+pub trait Identifier: Send + Sync {
+    /// Candidate names for the photo `filename` with content `photo` taken by
+    /// `camera_id`, best first, at most `count` of them.
+    fn suggest(
+        &self,
+        conn: &SqliteConnection,
+        filename: &str,
+        photo: &[u8],
+        camera_id: &str,
+        count: usize,
+    ) -> Result<Vec<Identification>, Box<dyn std::error::Error>>;
+
+    /// Learn the photo `filename` just stored, so it counts once named.
+    fn remember(&self, filename: &str, photo: &[u8]) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Tell who is in the photo, `None` if there is no guess at all.
+    fn identify(
+        &self,
+        conn: &SqliteConnection,
+        filename: &str,
+        photo: &[u8],
+        camera_id: &str,
+    ) -> Result<Option<Identification>, Box<dyn std::error::Error>> {
+        Ok(self
+            .suggest(conn, filename, photo, camera_id, 1)?
+            .into_iter()
+            .next())
+    }
+}
+
+const HIST_BINS: u32 = 4;
+const THUMB_SIDE: u32 = 16;
+
+/// Colour histogram (pose independent) followed by a tiny grayscale thumbnail
+/// (shape/pattern), each half normalized so the whole vector has unit length.
+struct Embedding(Vec<f32>);
+
+impl Embedding {
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let img = image::load_from_memory(bytes)?;
+
+        let mut hist = vec![0f32; (HIST_BINS * HIST_BINS * HIST_BINS) as usize];
+        let small = img.resize_exact(64, 64, FilterType::Triangle).to_rgb8();
+        for p in small.pixels() {
+            let [r, g, b] = p.0.map(|c| c as u32 * HIST_BINS / 256);
+            hist[(r * HIST_BINS * HIST_BINS + g * HIST_BINS + b) as usize] += 1.0;
+        }
+
+        let mut thumb = img
+            .resize_exact(THUMB_SIDE, THUMB_SIDE, FilterType::Triangle)
+            .to_luma8()
+            .pixels()
+            .map(|p| p.0[0] as f32)
+            .collect::<Vec<f32>>();
+        let mean = thumb.iter().sum::<f32>() / thumb.len() as f32;
+        thumb.iter_mut().for_each(|x| *x -= mean);
+
+        normalize(&mut hist);
+        normalize(&mut thumb);
+        hist.extend(thumb);
+        hist.iter_mut().for_each(|x| *x *= std::f32::consts::FRAC_1_SQRT_2);
+        Ok(Embedding(hist))
+    }
+
+    fn similarity(&self, other: &Embedding) -> f32 {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .map(|(a, b)| a * b)
+            .sum::<f32>()
+            .max(0.0)
+    }
+}
+
+fn normalize(v: &mut [f32]) {
+    let len = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if len > 0.0 {
+        v.iter_mut().for_each(|x| *x /= len);
+    }
+}
+
+/// CPU only k-nearest-neighbour matcher over all named photos.
+///
+/// Embeddings are kept in memory, made by `warm_up` for the photos stored
+/// before start and by `remember` for those uploaded since. Names are always
+/// read from the database so renaming is picked up. The visual vote is mixed
+/// with how often a name shows up at the same camera.
+pub struct NearestNeighbour {
+    store: Arc<dyn PhotoStore>,
+    k: usize,
+    camera_weight: f32,
+    embeddings: Arc<Mutex<HashMap<String, Embedding>>>,
+}
+
+impl NearestNeighbour {
+    pub fn new(store: Arc<dyn PhotoStore>, k: usize, camera_weight: f32) -> Self {
+        Self {
+            store,
+            k,
+            camera_weight,
+            embeddings: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Embed the photos stored so far on a thread of its own, reading them
+    /// without holding up identifying. Photos not embedded yet are left out
+    /// of suggestions meanwhile.
+    pub fn warm_up(&self, conn: &SqliteConnection) -> QueryResult<()> {
+        let filenames: Vec<String> = photos::table.select(photos::filename).load(conn)?;
+        let store = self.store.clone();
+        let embeddings = self.embeddings.clone();
+        std::thread::spawn(move || {
+            for filename in filenames {
+                let embedding = store
+                    .get(&filename)
+                    .and_then(|x| x.ok_or_else(|| "Photo is missing".into()))
+                    .and_then(|x| Embedding::from_bytes(&x));
+                match (embedding, embeddings.lock()) {
+                    (Ok(e), Ok(mut embeddings)) => {
+                        embeddings.entry(filename).or_insert(e);
+                    }
+                    (Err(e), _) => eprintln!("Skip {} in identifying: {}", filename, e),
+                    (_, Err(_)) => return,
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+impl Identifier for NearestNeighbour {
+    fn suggest(
+        &self,
+        conn: &SqliteConnection,
+        filename: &str,
+        photo: &[u8],
+        camera_id: &str,
+        count: usize,
+    ) -> Result<Vec<Identification>, Box<dyn std::error::Error>> {
+        let named: Vec<(String, String, Option<String>)> = photos::table
+            .select((photos::filename, photos::camera_id, photos::name))
+            .filter(photos::name.is_not_null())
+            .filter(photos::filename.ne(filename))
+            .load(conn)?;
+        let target = Embedding::from_bytes(photo)?;
+
+        let mut camera_votes: HashMap<String, f32> = HashMap::new();
+        let mut camera_total = 0f32;
+        let embeddings = self.embeddings.lock().map_err(|e| e.to_string())?;
+        let mut neighbours = named
+            .into_iter()
+            .filter_map(|(filename, camera, name)| {
+                let name = name?;
+                if camera == camera_id {
+                    *camera_votes.entry(name.clone()).or_default() += 1.0;
+                    camera_total += 1.0;
+                }
+                let embedding = embeddings.get(&filename)?;
+                Some((name, target.similarity(embedding)))
+            })
+            .collect::<Vec<(String, f32)>>();
+        drop(embeddings);
+
+        neighbours.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let mut scores: HashMap<String, f32> = HashMap::new();
+        for (name, similarity) in neighbours.into_iter().take(self.k) {
+            *scores.entry(name).or_default() +=
+                similarity / self.k as f32 * (1.0 - self.camera_weight);
+        }
+        for (name, votes) in camera_votes {
+            *scores.entry(name).or_default() += votes / camera_total * self.camera_weight;
+        }
+        let mut suggestions = scores
+            .into_iter()
+            .map(|(name, confidence)| Identification { name, confidence })
+            .collect::<Vec<Identification>>();
+        suggestions.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        suggestions.truncate(count);
+        Ok(suggestions)
+    }
+    fn remember(&self, filename: &str, photo: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        let embedding = Embedding::from_bytes(photo)?;
+        self.embeddings
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(filename.to_string(), embedding);
+        Ok(())
+    }
+}
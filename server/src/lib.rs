@@ -1,2 +1,18 @@
 #[macro_use] extern crate diesel;
+#[cfg(test)]
+#[macro_use]
+extern crate diesel_migrations;
+pub mod cameras;
+pub mod capture;
+pub mod db;
+pub mod dedup;
+pub mod identify;
+pub mod media;
+pub mod naming;
+pub mod profiles;
+pub mod records;
 pub mod schema;
+pub mod settings;
+pub mod stats;
+pub mod storage;
+pub mod visits;
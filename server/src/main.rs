@@ -4,21 +4,68 @@
 extern crate rocket_contrib;
 #[macro_use]
 extern crate diesel;
+#[macro_use]
+extern crate diesel_migrations;
 
 use chrono::prelude::*;
+use diesel::insert_into;
 use diesel::prelude::*;
-use diesel::{insert_into, update};
-use jian_ai_server::schema::photos;
-use rocket::response::Debug;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use jian_ai_api::error::ErrorBody;
+use jian_ai_api::names::Names;
+use jian_ai_api::{APIS, DB, PICS, THUMBS};
+use jian_ai_server::cameras::{self, Camera, CameraChanges, NewCamera, Overview};
+use jian_ai_server::capture;
+use jian_ai_server::db::{self, DbPool};
+use jian_ai_server::dedup::{self, Existing};
+use jian_ai_server::identify::{Identification, Identifier, NearestNeighbour};
+use jian_ai_server::media;
+use jian_ai_server::naming::{self, NameChange, NameCount, NewName, Removal};
+use jian_ai_server::profiles::{self, Profile, ProfileChanges};
+use jian_ai_server::records;
+use jian_ai_server::settings::Settings;
+use jian_ai_server::stats::{self, Consumption, Count, HourlyActivity, Window};
+use jian_ai_server::storage::PhotoStore;
+use jian_ai_server::visits::{self, Visit};
+use rocket::config::{Table, Value};
+use rocket::handler::Outcome;
+use rocket::http::uri::Segments;
+use rocket::http::{ContentType, Method, Status};
+use rocket::request::{self, FromRequest};
+use rocket::response::{self, Content, NamedFile, Responder};
 use rocket::*;
+use rocket_contrib::databases::database_config;
 use rocket_contrib::json::Json as RJson;
-use rocket_contrib::serve::StaticFiles;
-use std::fs::create_dir_all;
+use std::io::Read;
+use std::ops::Deref;
 use std::path::PathBuf;
+use std::sync::Arc;
 //use jian_ai_server::schema::names;
 
-#[database("jian_ai")]
-struct DbConn(diesel::SqliteConnection);
+embed_migrations!();
+
+/// A connection from the pool, with the pragmas of `db::pool` applied.
+struct DbConn(PooledConnection<ConnectionManager<SqliteConnection>>);
+
+impl Deref for DbConn {
+    type Target = SqliteConnection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for DbConn {
+    type Error = ();
+
+    fn from_request(req: &'a Request<'r>) -> request::Outcome<Self, ()> {
+        let pool = req.guard::<State<DbPool>>()?;
+        match pool.get() {
+            Ok(conn) => request::Outcome::Success(DbConn(conn)),
+            Err(_) => request::Outcome::Failure((Status::ServiceUnavailable, ())),
+        }
+    }
+}
 
 #[derive(Queryable, Insertable)]
 struct Photo {
@@ -27,207 +74,808 @@ struct Photo {
     camera_id: String,
     food_weight: i16,
     name: Option<String>,
+    confidence: Option<f32>,
+    datetime_source: Option<String>,
+    content_hash: Option<String>,
+    perceptual_hash: Option<i64>,
+    width: Option<i32>,
+    height: Option<i32>,
+}
+
+/// Photos whose perceptual hashes differ in no more bits than this look the same.
+const NEAR_DUPLICATE_DISTANCE: u32 = 5;
+
+/// Photos in a page of `/photos` unless asked otherwise, and at most.
+const PHOTOS_PAGE: i64 = 100;
+const MAX_PHOTOS_PAGE: i64 = 1000;
+
+/// What handlers fail with, answered as an `ErrorBody`.
+struct ApiError {
+    status: Status,
+    body: ErrorBody,
+}
+
+impl ApiError {
+    fn new(status: Status, code: &str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: ErrorBody {
+                code: code.to_string(),
+                message: message.into(),
+                details: None,
+            },
+        }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(Status::BadRequest, "bad_request", message)
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        Self::new(Status::NotFound, "not_found", message)
+    }
+
+    fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(Status::Forbidden, "forbidden", message)
+    }
+
+    fn conflict(message: impl Into<String>) -> Self {
+        Self::new(Status::Conflict, "conflict", message)
+    }
+
+    fn too_large(message: impl Into<String>) -> Self {
+        Self::new(Status::PayloadTooLarge, "too_large", message)
+    }
+
+    fn unsupported(message: impl Into<String>) -> Self {
+        Self::new(Status::UnsupportedMediaType, "unsupported", message)
+    }
+
+    fn internal(message: impl Into<String>, details: impl ToString) -> Self {
+        Self::new(Status::InternalServerError, "internal", message).with_details(details)
+    }
+
+    fn with_details(mut self, details: impl ToString) -> Self {
+        self.body.details = Some(details.to_string());
+        self
+    }
+}
+
+impl From<diesel::result::Error> for ApiError {
+    fn from(e: diesel::result::Error) -> Self {
+        use diesel::result::{DatabaseErrorKind, Error};
+        match e {
+            Error::NotFound => ApiError::not_found("Not found"),
+            Error::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+                ApiError::conflict("Already exists").with_details(info.message())
+            }
+            Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info) => {
+                ApiError::conflict("Refers to something missing, or is still referred to")
+                    .with_details(info.message())
+            }
+            e => ApiError::internal("Database failed", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for ApiError {
+    fn from(e: std::io::Error) -> Self {
+        ApiError::internal("Reading or writing failed", e)
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for ApiError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        ApiError::internal("Internal error", e)
+    }
+}
+
+impl From<diesel_migrations::RunMigrationsError> for ApiError {
+    fn from(e: diesel_migrations::RunMigrationsError) -> Self {
+        ApiError::internal("Migrating failed", e)
+    }
+}
+
+impl<'r> Responder<'r> for ApiError {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        if self.status == Status::InternalServerError {
+            eprintln!("{}", self.body);
+        }
+        Response::build_from(RJson(self.body).respond_to(req)?)
+            .status(self.status)
+            .ok()
+    }
+}
+
+/// Rocket's own failures, like a body that is not the JSON expected, in the same shape.
+#[catch(400)]
+fn bad_request(_req: &Request) -> ApiError {
+    ApiError::bad_request("Bad request")
+}
+
+#[catch(404)]
+fn not_found(req: &Request) -> ApiError {
+    ApiError::not_found(format!("Nothing at {}", req.uri()))
+}
+
+#[catch(422)]
+fn unprocessable(_req: &Request) -> ApiError {
+    ApiError::new(
+        Status::UnprocessableEntity,
+        "unprocessable",
+        "The body is not what is expected",
+    )
 }
 
-#[post("/new_image?<camera_id>&<food_weight>", data = "<data>")]
+#[catch(500)]
+fn internal(_req: &Request) -> ApiError {
+    ApiError::new(Status::InternalServerError, "internal", "Internal error")
+}
+
+#[catch(503)]
+fn unavailable(_req: &Request) -> ApiError {
+    ApiError::new(
+        Status::ServiceUnavailable,
+        "unavailable",
+        "Database is not available",
+    )
+}
+
+#[derive(Responder)]
+enum Upload {
+    #[response(status = 200)]
+    Stored(()),
+    /// The same photo was uploaded before, nothing is stored.
+    #[response(status = 409)]
+    Duplicate(RJson<Existing>),
+}
+
+/// `taken_at` is seconds since the epoch, used when the photo has no EXIF time.
+#[post(
+    "/new_image?<camera_id>&<food_weight>&<firmware>&<taken_at>",
+    data = "<data>"
+)]
 fn new_image(
     db: DbConn,
+    identifier: State<Box<dyn Identifier>>,
+    store: State<Arc<dyn PhotoStore>>,
+    settings: State<Settings>,
     camera_id: String,
     food_weight: i16,
+    firmware: Option<String>,
+    taken_at: Option<i64>,
     data: Data,
-) -> Result<(), Debug<Box<dyn std::error::Error>>> {
+) -> Result<Upload, ApiError> {
     use jian_ai_server::schema::photos::dsl as photo;
+    let known = cameras::heartbeat(&*db, &camera_id, firmware.as_deref())?;
+    if !known {
+        return Err(ApiError::forbidden(format!(
+            "Camera {} is not registered, or retired",
+            camera_id
+        )));
+    }
+    let mut bytes = vec![];
+    data.open()
+        .take(settings.max_upload + 1)
+        .read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > settings.max_upload {
+        return Err(ApiError::too_large(format!(
+            "Photos are up to {} bytes",
+            settings.max_upload
+        )));
+    }
+    let (format, img) = match media::validate(&bytes) {
+        Ok(x) => x,
+        // Not an image, or not one of the formats taken.
+        Err(e) => return Err(ApiError::unsupported(e)),
+    };
     let utc: DateTime<Utc> = Utc::now();
-    let filename = format!("{}-{}.jpg", camera_id, utc.format("%Y%m%d_%H%M%S_%f"));
-    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "pics", &filename]
-        .iter()
-        .collect();
-    eprintln!("{}", path.as_path().display());
-    data.stream_to_file(path).map_err(|x| Debug(x.into()))?;
-    // identify -> name
+    let filename = format!(
+        "{}-{}.{}",
+        camera_id,
+        utc.format("%Y%m%d_%H%M%S_%f"),
+        media::extension(format)
+    );
+    let content_hash = dedup::content_hash(&bytes);
+    if let Some(existing) = dedup::find(&*db, &content_hash)? {
+        return Ok(Upload::Duplicate(RJson(existing)));
+    }
+    store.put(&filename, &bytes)?;
+    if let Err(e) = media::make_thumbnails(&**store, &filename, &img) {
+        eprintln!("Making thumbnails of {} failed: {}", filename, e);
+    }
+    let timezone = cameras::get(&*db, &camera_id)?.and_then(|x| x.timezone);
+    let (datetime, source) = capture::taken_at(
+        &bytes,
+        timezone.as_deref(),
+        taken_at.and_then(|x| DateTime::from_timestamp(x, 0).map(|d| d.naive_utc())),
+    );
+    let identification = identifier
+        .identify(&*db, &filename, &bytes, &camera_id)
+        .unwrap_or_else(|e| {
+            eprintln!("Identifying {} failed: {}", filename, e);
+            None
+        })
+        .filter(|x| x.confidence >= settings.identify_threshold);
     let pic = Photo {
-        datetime: None,
+        datetime: Some(datetime),
         filename,
         camera_id,
         food_weight,
-        name: None,
+        name: identification.as_ref().map(|x| x.name.clone()),
+        confidence: identification.map(|x| x.confidence),
+        datetime_source: Some(source.as_str().to_string()),
+        content_hash: Some(content_hash.clone()),
+        perceptual_hash: Some(dedup::perceptual_hash(&img)),
+        width: Some(img.width() as i32),
+        height: Some(img.height() as i32),
     };
-    insert_into(photo::photos)
-        .values(&pic)
-        .execute(&*db)
-        .map_err(|x| Debug(x.into()))?;
-    Ok(())
+    if let Err(e) = insert_into(photo::photos).values(&pic).execute(&*db) {
+        if let Err(e) = media::remove(&**store, &pic.filename) {
+            eprintln!("Removing {} failed: {}", pic.filename, e);
+        }
+        // Lost to the same photo uploaded at the same time.
+        if let diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UniqueViolation,
+            _,
+        ) = e
+        {
+            if let Some(existing) = dedup::find(&*db, &content_hash)? {
+                return Ok(Upload::Duplicate(RJson(existing)));
+            }
+        }
+        return Err(e.into());
+    }
+    if let Err(e) = identifier.remember(&pic.filename, &bytes) {
+        eprintln!("Learning {} for identifying failed: {}", pic.filename, e);
+    }
+    if let Err(e) = visits::group(&*db, settings.visit_gap()) {
+        eprintln!("Grouping visits failed: {}", e);
+    }
+    Ok(Upload::Stored(()))
+}
+
+/// Groups of photos that look the same, though not byte identical.
+#[get("/near_duplicates?<max_distance>")]
+fn near_duplicates(
+    db: DbConn,
+    max_distance: Option<u32>,
+) -> Result<RJson<Vec<Vec<String>>>, ApiError> {
+    let clusters = dedup::near_duplicates(&*db, max_distance.unwrap_or(NEAR_DUPLICATE_DISTANCE))?;
+    Ok(RJson(clusters))
+}
+
+/// Hash and measure photos uploaded before that was done, and report those
+/// that turn out to be duplicates.
+#[post("/hash_photos")]
+fn hash_photos(
+    db: DbConn,
+    store: State<Arc<dyn PhotoStore>>,
+) -> Result<RJson<dedup::Backfill>, ApiError> {
+    let done = dedup::backfill(&*db, &**store)?;
+    Ok(RJson(done))
+}
+
+#[get("/<filename>")]
+fn pic(store: State<Arc<dyn PhotoStore>>, filename: String) -> Result<Content<Vec<u8>>, ApiError> {
+    let content_type =
+        ContentType::parse_flexible(media::content_type(&filename)).unwrap_or(ContentType::JPEG);
+    let photo = store
+        .get(&filename)?
+        .ok_or_else(|| ApiError::not_found(format!("No photo {}", filename)))?;
+    Ok(Content(content_type, photo))
+}
+
+/// Photos scaled to fit `size` pixels, one of `media::THUMB_SIZES`.
+#[get("/<size>/<filename>")]
+fn thumbnail(
+    store: State<Arc<dyn PhotoStore>>,
+    size: u32,
+    filename: String,
+) -> Result<Content<Vec<u8>>, ApiError> {
+    let thumb = media::thumbnail(&**store, size, &filename)?
+        .ok_or_else(|| ApiError::not_found(format!("No {} thumbnail of {}", size, filename)))?;
+    Ok(Content(ContentType::JPEG, thumb))
 }
 
 #[get("/names")]
-fn names(db: DbConn) -> Result<RJson<Vec<String>>, Debug<Box<dyn std::error::Error>>> {
+fn names(db: DbConn) -> Result<RJson<Names>, ApiError> {
     use jian_ai_server::schema::names::dsl as name;
-    let vec = name::names
-        .select(name::name)
-        .load(&*db)
-        .map_err(|x| Debug(x.into()))?;
+    let vec = name::names.select(name::name).load(&*db)?;
+    Ok(RJson(vec))
+}
+
+#[get("/names/<name>/profile")]
+fn get_profile(db: DbConn, name: String) -> Result<RJson<Profile>, ApiError> {
+    let profile = profiles::get(&*db, &name)?
+        .ok_or_else(|| ApiError::not_found(format!("No name {}", name)))?;
+    Ok(RJson(profile))
+}
+
+#[put("/names/<name>/profile", data = "<changes>")]
+fn change_profile(
+    db: DbConn,
+    name: String,
+    changes: RJson<ProfileChanges>,
+) -> Result<(), ApiError> {
+    profiles::check(&changes).map_err(|(field, e)| ApiError::bad_request(e).with_details(field))?;
+    let found = profiles::change(&*db, &name, changes.into_inner())?;
+    if found {
+        Ok(())
+    } else {
+        Err(ApiError::not_found(format!("No name {}", name)))
+    }
+}
+
+#[post("/names/rename?<from>&<to>")]
+fn rename_name(db: DbConn, from: String, to: String) -> Result<(), ApiError> {
+    let to = naming::clean(&to).map_err(ApiError::bad_request)?;
+    let found = naming::rename(&*db, &from, to).map_err(|e| match e {
+        diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UniqueViolation,
+            _,
+        ) => ApiError::conflict(format!("Name {} exists already", to)),
+        e => e.into(),
+    })?;
+    if found {
+        Ok(())
+    } else {
+        Err(ApiError::not_found(format!("No name {}", from)))
+    }
+}
+
+#[post("/names/merge?<from>&<into>")]
+fn merge_name(db: DbConn, from: String, into: String) -> Result<(), ApiError> {
+    let into = naming::clean(&into).map_err(ApiError::bad_request)?;
+    let found = naming::merge(&*db, &from, into)?;
+    if found {
+        Ok(())
+    } else {
+        Err(ApiError::not_found(format!("No name {} or {}", from, into)))
+    }
+}
+
+#[get("/names/changes")]
+fn name_changes(db: DbConn) -> Result<RJson<Vec<NameChange>>, ApiError> {
+    let vec = naming::history(&*db)?;
     Ok(RJson(vec))
 }
 
 #[get("/unnamed_images")]
-fn unnamed_images(db: DbConn) -> Result<RJson<Vec<String>>, Debug<Box<dyn std::error::Error>>> {
-    use jian_ai_server::schema::photos::dsl as photo;
-    let vec = photo::photos
-        .select(photo::filename)
-        .filter(photo::name.is_null())
-        .load(&*db)
-        .map_err(|x| Debug(x.into()))?;
+fn unnamed_images(db: DbConn) -> Result<RJson<Vec<records::Photo>>, ApiError> {
+    let vec = records::unnamed(&*db)?;
     Ok(RJson(vec))
 }
 
+/// `since` and `until` are like `2021-05-29T14:56:30`, in UTC. `after` is
+/// `next` of the previous page.
+#[get("/photos?<camera_id>&<name>&<named>&<since>&<until>&<min_weight>&<max_weight>&<order>&<after>&<limit>")]
+fn list_photos(
+    db: DbConn,
+    camera_id: Option<String>,
+    name: Option<String>,
+    named: Option<bool>,
+    since: Option<String>,
+    until: Option<String>,
+    min_weight: Option<i16>,
+    max_weight: Option<i16>,
+    order: Option<String>,
+    after: Option<String>,
+    limit: Option<i64>,
+) -> Result<RJson<records::Page>, ApiError> {
+    let datetime = |x: Option<String>| {
+        x.map(|x| {
+            x.parse::<NaiveDateTime>()
+                .map_err(|e| ApiError::bad_request(format!("Bad time {}: {}", x, e)))
+        })
+        .transpose()
+    };
+    let filter = records::Filter {
+        camera_id,
+        name,
+        named,
+        since: datetime(since)?,
+        until: datetime(until)?,
+        min_weight,
+        max_weight,
+    };
+    let order = order
+        .as_deref()
+        .unwrap_or("newest")
+        .parse::<records::Order>()
+        .map_err(ApiError::bad_request)?;
+    let limit = limit.unwrap_or(PHOTOS_PAGE).clamp(1, MAX_PHOTOS_PAGE);
+    let page =
+        records::list(&*db, &filter, order, after.as_deref(), limit).map_err(|e| match e {
+            diesel::result::Error::NotFound => ApiError::not_found(format!(
+                "No photo {} to page after",
+                after.unwrap_or_default()
+            )),
+            e => e.into(),
+        })?;
+    Ok(RJson(page))
+}
+
 #[post("/name_image?<photo_filename>&<name>")]
-fn name_image(
+fn name_image(db: DbConn, photo_filename: String, name: String) -> Result<(), ApiError> {
+    if records::name(&*db, &photo_filename, Some(&name))? {
+        Ok(())
+    } else {
+        Err(ApiError::not_found(format!("No photo {}", photo_filename)))
+    }
+}
+
+/// Forget who is in the photo, it is back for tagging.
+#[post("/unname_image?<photo_filename>")]
+fn unname_image(db: DbConn, photo_filename: String) -> Result<(), ApiError> {
+    if records::name(&*db, &photo_filename, None)? {
+        Ok(())
+    } else {
+        Err(ApiError::not_found(format!("No photo {}", photo_filename)))
+    }
+}
+
+#[get("/suggest_names?<photo_filename>&<count>")]
+fn suggest_names(
     db: DbConn,
+    identifier: State<Box<dyn Identifier>>,
+    store: State<Arc<dyn PhotoStore>>,
     photo_filename: String,
-    name: String,
-) -> Result<(), Debug<Box<dyn std::error::Error>>> {
+    count: Option<usize>,
+) -> Result<RJson<Vec<Identification>>, ApiError> {
     use jian_ai_server::schema::photos::dsl as photo;
-    update(photo::photos.filter(photo::filename.eq(photo_filename)))
-        .set(photos::name.eq(Some(name)))
-        .execute(&*db)
-        .map_err(|x| Debug(x.into()))?;
+    let camera_id: String = photo::photos
+        .select(photo::camera_id)
+        .filter(photo::filename.eq(&photo_filename))
+        .first(&*db)
+        .optional()?
+        .ok_or_else(|| ApiError::not_found(format!("No photo {}", photo_filename)))?;
+    let photo = store
+        .get(&photo_filename)?
+        .ok_or_else(|| ApiError::internal("Photo is missing from the store", &photo_filename))?;
+    let suggestions = identifier.suggest(
+        &*db,
+        &photo_filename,
+        &photo,
+        &camera_id,
+        count.unwrap_or(5),
+    )?;
+    Ok(RJson(suggestions))
+}
+
+#[get("/visits?<camera_id>&<unnamed>")]
+fn list_visits(
+    db: DbConn,
+    camera_id: Option<String>,
+    unnamed: Option<bool>,
+) -> Result<RJson<Vec<Visit>>, ApiError> {
+    use jian_ai_server::schema::visits::dsl as visit;
+    let mut query = visit::visits.order(visit::start_time.desc()).into_boxed();
+    if let Some(camera_id) = camera_id {
+        query = query.filter(visit::camera_id.eq(camera_id));
+    }
+    if unnamed == Some(true) {
+        query = query.filter(visit::name.is_null());
+    }
+    let vec = query.load(&*db)?;
+    Ok(RJson(vec))
+}
+
+#[post("/name_visit?<visit_id>&<name>")]
+fn name_visit(db: DbConn, visit_id: i32, name: String) -> Result<(), ApiError> {
+    let name = naming::clean(&name).map_err(ApiError::bad_request)?;
+    if visits::name(&*db, visit_id, name)? {
+        Ok(())
+    } else {
+        Err(ApiError::not_found(format!("No visit {}", visit_id)))
+    }
+}
+
+#[post("/group_visits")]
+fn group_visits(db: DbConn, settings: State<Settings>) -> Result<RJson<usize>, ApiError> {
+    let grouped = visits::group(&*db, settings.visit_gap())?;
+    Ok(RJson(grouped))
+}
+
+#[get("/stats/consumption/names?<window>")]
+fn consumption_by_name(
+    db: DbConn,
+    window: Option<String>,
+) -> Result<RJson<Vec<Consumption>>, ApiError> {
+    let window = window
+        .as_deref()
+        .unwrap_or("day")
+        .parse::<Window>()
+        .map_err(ApiError::bad_request)?;
+    let vec = stats::by_name(&*db, window)?;
+    Ok(RJson(vec))
+}
+
+#[get("/stats/consumption/cameras?<window>")]
+fn consumption_by_camera(
+    db: DbConn,
+    window: Option<String>,
+) -> Result<RJson<Vec<Consumption>>, ApiError> {
+    let window = window
+        .as_deref()
+        .unwrap_or("day")
+        .parse::<Window>()
+        .map_err(ApiError::bad_request)?;
+    let vec = stats::by_camera(&*db, window)?;
+    Ok(RJson(vec))
+}
+
+#[get("/stats/visits?<window>")]
+fn visits_by_name(db: DbConn, window: Option<String>) -> Result<RJson<Vec<Count>>, ApiError> {
+    let window = window
+        .as_deref()
+        .unwrap_or("day")
+        .parse::<Window>()
+        .map_err(ApiError::bad_request)?;
+    let vec = stats::visits_by_name(&*db, window)?;
+    Ok(RJson(vec))
+}
+
+#[get("/stats/hourly_activity")]
+fn hourly_activity(db: DbConn) -> Result<RJson<Vec<HourlyActivity>>, ApiError> {
+    let vec = stats::hourly_activity(&*db)?;
+    Ok(RJson(vec))
+}
+
+#[get("/cameras")]
+fn list_cameras(db: DbConn) -> Result<RJson<Vec<Camera>>, ApiError> {
+    let vec = cameras::list(&*db)?;
+    Ok(RJson(vec))
+}
+
+#[get("/cameras/<id>")]
+fn get_camera(db: DbConn, id: String) -> Result<RJson<Camera>, ApiError> {
+    let camera =
+        cameras::get(&*db, &id)?.ok_or_else(|| ApiError::not_found(format!("No camera {}", id)))?;
+    Ok(RJson(camera))
+}
+
+#[post("/cameras", data = "<camera>")]
+fn create_camera(db: DbConn, camera: RJson<NewCamera>) -> Result<(), ApiError> {
+    cameras::create(&*db, &camera)?;
     Ok(())
 }
 
+#[put("/cameras/<id>", data = "<changes>")]
+fn change_camera(db: DbConn, id: String, changes: RJson<CameraChanges>) -> Result<(), ApiError> {
+    let found = cameras::change(&*db, &id, &changes)?;
+    if found {
+        Ok(())
+    } else {
+        Err(ApiError::not_found(format!("No camera {}", id)))
+    }
+}
+
+#[delete("/cameras/<id>")]
+fn remove_camera(db: DbConn, id: String) -> Result<(), ApiError> {
+    let found = cameras::remove(&*db, &id)?;
+    if found {
+        Ok(())
+    } else {
+        Err(ApiError::not_found(format!("No camera {}", id)))
+    }
+}
+
+#[get("/cameras/overview")]
+fn camera_overview(db: DbConn) -> Result<RJson<Vec<Overview>>, ApiError> {
+    let vec = cameras::overview(&*db)?;
+    Ok(RJson(vec))
+}
+
+#[post("/cameras/<id>/rename?<display_name>")]
+fn rename_camera(db: DbConn, id: String, display_name: Option<String>) -> Result<(), ApiError> {
+    let found = cameras::rename(&*db, &id, display_name.as_deref())?;
+    if found {
+        Ok(())
+    } else {
+        Err(ApiError::not_found(format!("No camera {}", id)))
+    }
+}
+
+#[post("/cameras/<id>/relocate?<location>")]
+fn relocate_camera(db: DbConn, id: String, location: Option<String>) -> Result<(), ApiError> {
+    let found = cameras::relocate(&*db, &id, location.as_deref())?;
+    if found {
+        Ok(())
+    } else {
+        Err(ApiError::not_found(format!("No camera {}", id)))
+    }
+}
+
+#[post("/cameras/<id>/retire?<retired>")]
+fn retire_camera(db: DbConn, id: String, retired: Option<bool>) -> Result<(), ApiError> {
+    let found = cameras::retire(&*db, &id, retired.unwrap_or(true))?;
+    if found {
+        Ok(())
+    } else {
+        Err(ApiError::not_found(format!("No camera {}", id)))
+    }
+}
+
 #[post("/new_names?<names>")]
-fn new_names(db: DbConn, names: String) -> Result<(), Debug<Box<dyn std::error::Error>>> {
-    use jian_ai_server::schema::names::dsl as name;
-    names
-        .split(',')
-        .map(|name| {
-            insert_into(name::names)
-                .values(name::name.eq(name))
-                .execute(&*db)
-        })
-        .collect::<QueryResult<Vec<usize>>>()
-        .map_err(|x| Debug(x.into()))?;
-    Ok(())
+fn new_names(db: DbConn, names: String) -> RJson<Vec<NewName>> {
+    RJson(naming::add(&*db, &names))
 }
 
-#[derive(QueryableByName)]
-struct Useless {
-    #[sql_type = "diesel::sql_types::Text"]
-    _ret: String,
+#[get("/names/counts")]
+fn name_counts(db: DbConn) -> Result<RJson<Vec<NameCount>>, ApiError> {
+    let vec = naming::counts(&*db)?;
+    Ok(RJson(vec))
 }
 
-#[get("/init")]
-fn db_init(db: DbConn) -> Result<String, Debug<Box<dyn std::error::Error>>> {
-    // let confs = ["PRAGMA journal_mode = WAL", "PRAGMA synchronous = NORMAL", "PRAGMA foreign_keys = ON", "PRAGMA busy_timeout = 15"];
-    // for conf in confs {
-    //     let _x = diesel::sql_query(conf).load::<Useless>(&*db);
-    // }
-    // Ok(())
-    Ok(["journal_mode", "busy_timeout", "foreign_keys"]
-        .iter()
-        .map(|t| {
-            let ret: Result<Vec<String>, diesel::result::Error> =
-                diesel::dsl::sql::<diesel::sql_types::Text>(&format!("select * from pragma_{}", t))
-                    .load(&*db);
-            ret.unwrap().first().unwrap().clone()
-        })
-        .collect::<Vec<String>>()
-        .join("..."))
+#[delete("/names/<name>")]
+fn remove_name(db: DbConn, name: String) -> Result<Status, ApiError> {
+    match naming::remove_unused(&*db, &name)? {
+        Removal::Removed => Ok(Status::NoContent),
+        Removal::NotFound => Err(ApiError::not_found(format!("No name {}", name))),
+        Removal::InUse => Err(ApiError::conflict(format!(
+            "Name {} is still used by photos or visits",
+            name
+        ))),
+    }
 }
 
-// #[get("/<file..>")]
-// fn pics(file: PathBuf) -> Result<rocket::response::NamedFile, Box<dyn std::error::Error>> {
-//     let content = rocket::response::NamedFile::open([env!("CARGO_MANIFEST_DIR"), "pics"].iter().collect::<PathBuf>().join(file))?;
-//     Ok(content)
-// }
+/// Applies migrations not applied yet, they are also applied on start.
+#[post("/init")]
+fn db_init(db: DbConn) -> Result<RJson<db::Status>, ApiError> {
+    embedded_migrations::run_with_output(&*db, &mut std::io::stderr())?;
+    let status = db::status(&*db)?;
+    Ok(RJson(status))
+}
+
+#[get("/status")]
+fn db_status(db: DbConn) -> Result<RJson<db::Status>, ApiError> {
+    let status = db::status(&*db)?;
+    Ok(RJson(status))
+}
+
+fn settings(config: &rocket::Config) -> Result<Settings, Box<dyn std::error::Error>> {
+    let extras: Table = config
+        .extras
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let mut settings: Settings = Value::Table(extras).try_into()?;
+    settings.pics = config.root_relative(&settings.pics);
+    settings.frontend = config.root_relative(&settings.frontend);
+    Ok(settings)
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    create_dir_all(
-        [env!("CARGO_MANIFEST_DIR"), "pics"]
-            .iter()
-            .collect::<PathBuf>(),
-    )?;
-    rocket::ignite()
-        .attach(DbConn::fairing())
-        .mount("/db", routes![db_init])
+    let rocket = rocket::ignite();
+    let settings = settings(rocket.config())?;
+    let store = settings.photo_store()?;
+    let frontend = settings.frontend.clone();
+    let database = database_config("jian_ai", rocket.config())
+        .map_err(|e| format!("Database jian_ai is not configured: {:?}", e))?;
+    let pool = db::pool(database.url, database.pool_size, settings.busy_timeout_ms)?;
+    embedded_migrations::run_with_output(&*pool.get()?, &mut std::io::stderr())?;
+    let identifier = NearestNeighbour::new(
+        store.clone(),
+        settings.identify_k,
+        settings.identify_camera_weight,
+    );
+    identifier.warm_up(&*pool.get()?)?;
+    rocket
+        .manage(pool)
+        .manage(Box::new(identifier) as Box<dyn Identifier>)
+        .manage(store)
+        .manage(settings)
+        .register(catchers![
+            bad_request,
+            not_found,
+            unprocessable,
+            internal,
+            unavailable
+        ])
+        .mount(DB, routes![db_init, db_status])
         .mount(
-            "/apis",
-            routes![new_image, names, unnamed_images, name_image, new_names],
+            APIS,
+            routes![
+                new_image,
+                near_duplicates,
+                hash_photos,
+                names,
+                get_profile,
+                change_profile,
+                rename_name,
+                merge_name,
+                name_changes,
+                unnamed_images,
+                list_photos,
+                name_image,
+                unname_image,
+                suggest_names,
+                new_names,
+                name_counts,
+                remove_name,
+                list_visits,
+                name_visit,
+                group_visits,
+                consumption_by_name,
+                consumption_by_camera,
+                visits_by_name,
+                hourly_activity,
+                list_cameras,
+                get_camera,
+                create_camera,
+                change_camera,
+                remove_camera,
+                camera_overview,
+                rename_camera,
+                relocate_camera,
+                retire_camera
+            ],
         )
+        .mount(THUMBS, routes![thumbnail])
+        .mount(PICS, routes![pic])
         .mount(
-            "/pics",
-            StaticFiles::from(concat!(env!("CARGO_MANIFEST_DIR"), "/pics")),
+            "/",
+            YewFiles {
+                root: frontend,
+                default_page: ["index.html"].iter().collect::<PathBuf>(),
+                rank: 100,
+            },
         )
-        // .mount(
-        //     "/",
-        //     YewFiles {
-        //         root: [env!("CARGO_MANIFEST_DIR"), "webpages", "static"]
-        //             .iter()
-        //             .collect::<PathBuf>(),
-        //         default_page: ["index.html"].iter().collect::<PathBuf>(),
-        //         rank: 100,
-        //     },
-        // ) // StaticFiles::from([env!("CARGO_MANIFEST_DIR"), "webpages", "static"].iter().collect::<PathBuf>()))
         .launch();
 
     Ok(())
 }
 
-// use rocket::handler::Outcome;
-// use rocket::http::uri::Segments;
-// use rocket::http::{Method, Status};
-// use rocket::response::NamedFile;
-
-// #[derive(Clone)]
-// pub struct YewFiles {
-//     root: PathBuf,
-//     default_page: PathBuf,
-//     rank: isize,
-// }
-
-// type Routes = Vec<Route>;
-// impl From<YewFiles> for Routes {
-//     fn from(yf: YewFiles) -> Self {
-//         vec![Route::ranked(yf.rank, Method::Get, "/<path..>", yf)]
-//     }
-// }
-
-// impl Handler for YewFiles {
-//     fn handle<'r>(&self, req: &'r Request<'_>, _data: Data) -> Outcome<'r> {
-//         if let Some(Ok(segs)) = req.get_segments::<Segments>(0) {
-//             if let Ok(path) = segs.into_path_buf(false) {
-//                 let full_path = self.root.join(path);
-//                 if full_path.is_file() {
-//                     Outcome::from(req, NamedFile::open(&full_path).ok())
-//                 } else {
-//                     Outcome::from(
-//                         req,
-//                         NamedFile::open(&self.root.join(&self.default_page)).ok(),
-//                     )
-//                 }
-//             } else {
-//                 Outcome::Failure(Status::NotFound)
-//             }
-//         } else {
-//             Outcome::Failure(Status::NotFound)
-//         }
-//     }
-// }
-
-// use rocket::fairing::{Fairing, Info, Kind};
-// struct CORS;
-// impl Fairing for CORS {
-//     fn info(&self) -> Info {
-//         Info {
-//             name: "cors",
-//             kind: Kind::Response, // | Kind::Singleton,
-//         }
-//     }
-
-//     fn on_response(&self, request: &Request<'_>, response: &mut Response<'_>) {
-//         response.set_header(
-//             rocket::http::hyper::header::AccessControlAllowOrigin::Value(
-//                 "http://localhost:8000/".to_string(),
-//             ),
-//         );
-//     }
-// }
+/// Serves the built `webpages`, with `default_page` for paths that are not
+/// files so yew-router can route them in the browser.
+#[derive(Clone)]
+pub struct YewFiles {
+    root: PathBuf,
+    default_page: PathBuf,
+    rank: isize,
+}
+
+/// Mounted beside the pages, unknown paths under these are just not found.
+const NOT_PAGES: [&str; 4] = [APIS, DB, PICS, THUMBS];
+
+type Routes = Vec<Route>;
+impl From<YewFiles> for Routes {
+    fn from(yf: YewFiles) -> Self {
+        vec![
+            Route::ranked(yf.rank, Method::Get, "/", yf.clone()),
+            Route::ranked(yf.rank, Method::Get, "/<path..>", yf),
+        ]
+    }
+}
+
+impl Handler for YewFiles {
+    fn handle<'r>(&self, req: &'r Request<'_>, _data: Data) -> Outcome<'r> {
+        let path = match req.get_segments::<Segments>(0) {
+            Some(Ok(segs)) => match segs.into_path_buf(false) {
+                Ok(path) => path,
+                Err(_) => return Outcome::Failure(Status::NotFound),
+            },
+            Some(Err(_)) => return Outcome::Failure(Status::NotFound),
+            // `/`
+            None => PathBuf::new(),
+        };
+        if let Some(first) = path.components().next() {
+            if NOT_PAGES
+                .iter()
+                .any(|x| first.as_os_str() == x.trim_start_matches('/'))
+            {
+                return Outcome::Failure(Status::NotFound);
+            }
+        }
+        let full_path = self.root.join(&path);
+        if full_path.is_file() {
+            Outcome::from(req, NamedFile::open(&full_path).ok())
+        } else {
+            Outcome::from(
+                req,
+                NamedFile::open(&self.root.join(&self.default_page)).ok(),
+            )
+        }
+    }
+}
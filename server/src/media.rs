@@ -0,0 +1,93 @@
+use crate::storage::PhotoStore;
+use image::{DynamicImage, ImageFormat, ImageOutputFormat};
+use std::io::Cursor;
+
+/// Longest side of the generated thumbnails, in pixels.
+pub const THUMB_SIZES: [u32; 2] = [160, 640];
+
+/// The format by magic bytes, for the formats taken.
+fn sniff(bytes: &[u8]) -> Option<ImageFormat> {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some(ImageFormat::Jpeg)
+    } else if bytes.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        Some(ImageFormat::Png)
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some(ImageFormat::WebP)
+    } else {
+        None
+    }
+}
+
+pub fn extension(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Png => "png",
+        ImageFormat::WebP => "webp",
+        _ => "jpg",
+    }
+}
+
+/// Content type for serving a stored photo by its filename.
+pub fn content_type(filename: &str) -> &'static str {
+    match filename.rsplit('.').next() {
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        _ => "image/jpeg",
+    }
+}
+
+/// Checks the upload is a JPEG, PNG or WebP that decodes, or says why not.
+pub fn validate(bytes: &[u8]) -> Result<(ImageFormat, DynamicImage), String> {
+    let format = sniff(bytes).ok_or("Not a JPEG, PNG or WebP image")?;
+    let img = image::load_from_memory_with_format(bytes, format)
+        .map_err(|e| format!("Cannot decode the image: {}", e))?;
+    Ok((format, img))
+}
+
+/// Thumbnails are JPEGs stored beside the originals, as `thumbs/<size>/<filename>.jpg`.
+fn thumbnail_key(size: u32, filename: &str) -> String {
+    format!("thumbs/{}/{}.jpg", size, filename)
+}
+
+pub fn make_thumbnails(
+    store: &dyn PhotoStore,
+    filename: &str,
+    img: &DynamicImage,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for size in THUMB_SIZES {
+        let mut jpeg = Cursor::new(vec![]);
+        DynamicImage::ImageRgb8(img.thumbnail(size, size).to_rgb8())
+            .write_to(&mut jpeg, ImageOutputFormat::Jpeg(85))?;
+        store.put(&thumbnail_key(size, filename), jpeg.get_ref())?;
+    }
+    Ok(())
+}
+
+/// Removes a photo and its thumbnails.
+pub fn remove(store: &dyn PhotoStore, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+    for size in THUMB_SIZES {
+        store.delete(&thumbnail_key(size, filename))?;
+    }
+    store.delete(filename)
+}
+
+/// The thumbnail of a photo, made now for photos from before thumbnails.
+/// `None` for sizes not generated and photos not there.
+pub fn thumbnail(
+    store: &dyn PhotoStore,
+    size: u32,
+    filename: &str,
+) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    if !THUMB_SIZES.contains(&size) {
+        return Ok(None);
+    }
+    if let Some(thumb) = store.get(&thumbnail_key(size, filename))? {
+        return Ok(Some(thumb));
+    }
+    match store.get(filename)? {
+        Some(original) => {
+            make_thumbnails(store, filename, &image::load_from_memory(&original)?)?;
+            store.get(&thumbnail_key(size, filename))
+        }
+        None => Ok(None),
+    }
+}
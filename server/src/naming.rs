@@ -0,0 +1,351 @@
+use crate::schema::{name_changes, names, photos, visits};
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::result::DatabaseErrorKind;
+use diesel::result::Error::DatabaseError;
+use diesel::sql_types::{BigInt, Text};
+use diesel::{delete, insert_into, sql_query, update};
+pub use jian_ai_api::names::{NameChange, NameCount, NewName};
+use std::collections::HashMap;
+
+/// Point everything referring `from` to `to`, drop `from` and record it.
+fn move_references(conn: &SqliteConnection, action: &str, from: &str, to: &str) -> QueryResult<()> {
+    let moved = update(photos::table.filter(photos::name.eq(from)))
+        .set(photos::name.eq(to))
+        .execute(conn)?;
+    update(visits::table.filter(visits::name.eq(from)))
+        .set(visits::name.eq(to))
+        .execute(conn)?;
+    delete(names::table.find(from)).execute(conn)?;
+    insert_into(name_changes::table)
+        .values((
+            name_changes::action.eq(action),
+            name_changes::old_name.eq(from),
+            name_changes::new_name.eq(to),
+            name_changes::photos.eq(moved as i32),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Give `from` a new name, keeping its profile. Fails if `to` is taken,
+/// returns false when there is no `from`.
+pub fn rename(conn: &SqliteConnection, from: &str, to: &str) -> QueryResult<bool> {
+    conn.transaction(|| {
+        let copied = sql_query(
+            "INSERT INTO names (name, headshot, aliases, sex, birth_date, neutered, description, notes, status) \
+             SELECT ?, headshot, aliases, sex, birth_date, neutered, description, notes, status FROM names WHERE name = ?",
+        )
+        .bind::<Text, _>(to)
+        .bind::<Text, _>(from)
+        .execute(conn)?;
+        if copied == 0 {
+            return Ok(false);
+        }
+        move_references(conn, "rename", from, to)?;
+        Ok(true)
+    })
+}
+
+/// `from` turns out to be the same animal as `into`. Its photos and visits
+/// go to `into`, it becomes an alias of `into` and fills the blanks of
+/// `into`'s profile. Returns false when either name does not exist.
+pub fn merge(conn: &SqliteConnection, from: &str, into: &str) -> QueryResult<bool> {
+    conn.transaction(|| {
+        let aliases: Vec<Option<String>> = names::table
+            .select(names::aliases)
+            .filter(names::name.eq_any(vec![from, into]))
+            .load(conn)?;
+        if aliases.len() != 2 || from == into {
+            return Ok(false);
+        }
+        let mut merged: Vec<&str> = vec![from];
+        for alias in aliases.iter().flatten().flat_map(|a| a.split(',')) {
+            if alias != into && !merged.contains(&alias) {
+                merged.push(alias);
+            }
+        }
+        sql_query(
+            "UPDATE names SET \
+             headshot = coalesce(headshot, (SELECT headshot FROM names WHERE name = ?1)), \
+             sex = coalesce(sex, (SELECT sex FROM names WHERE name = ?1)), \
+             birth_date = coalesce(birth_date, (SELECT birth_date FROM names WHERE name = ?1)), \
+             neutered = coalesce(neutered, (SELECT neutered FROM names WHERE name = ?1)), \
+             description = coalesce(description, (SELECT description FROM names WHERE name = ?1)), \
+             notes = coalesce(notes, (SELECT notes FROM names WHERE name = ?1)), \
+             aliases = ?2 \
+             WHERE name = ?3",
+        )
+        .bind::<Text, _>(from)
+        .bind::<Text, _>(merged.join(","))
+        .bind::<Text, _>(into)
+        .execute(conn)?;
+        move_references(conn, "merge", from, into)?;
+        Ok(true)
+    })
+}
+
+/// Latest first.
+pub fn history(conn: &SqliteConnection) -> QueryResult<Vec<NameChange>> {
+    name_changes::table
+        .order(name_changes::id.desc())
+        .load(conn)
+}
+
+/// Every name with how many photos carry it, alphabetical.
+pub fn counts(conn: &SqliteConnection) -> QueryResult<Vec<NameCount>> {
+    let all: Vec<String> = names::table
+        .select(names::name)
+        .order(names::name)
+        .load(conn)?;
+    let used: HashMap<Option<String>, i64> = photos::table
+        .group_by(photos::name)
+        .select((photos::name, sql::<BigInt>("count(*)")))
+        .filter(photos::name.is_not_null())
+        .load::<(Option<String>, i64)>(conn)?
+        .into_iter()
+        .collect();
+    Ok(all
+        .into_iter()
+        .map(|name| NameCount {
+            photos: used.get(&Some(name.clone())).copied().unwrap_or(0),
+            name,
+        })
+        .collect())
+}
+
+pub enum Removal {
+    Removed,
+    NotFound,
+    /// Photos or visits still carry the name.
+    InUse,
+}
+
+pub fn remove_unused(conn: &SqliteConnection, name: &str) -> QueryResult<Removal> {
+    conn.transaction(|| {
+        let photos_using: i64 = photos::table
+            .filter(photos::name.eq(name))
+            .count()
+            .get_result(conn)?;
+        let visits_using: i64 = visits::table
+            .filter(visits::name.eq(name))
+            .count()
+            .get_result(conn)?;
+        if photos_using + visits_using > 0 {
+            return Ok(Removal::InUse);
+        }
+        Ok(match delete(names::table.find(name)).execute(conn)? {
+            0 => Removal::NotFound,
+            _ => Removal::Removed,
+        })
+    })
+}
+
+/// A name as users type it: surrounding whitespace trimmed, not empty. No
+/// commas, as those separate aliases and names in lists.
+pub fn clean(name: &str) -> Result<&str, String> {
+    match name.trim() {
+        "" => Err("Name is empty".to_string()),
+        name if name.contains(',') => Err("Name has a comma".to_string()),
+        name => Ok(name),
+    }
+}
+
+/// Add comma separated `names`, each on its own so one bad entry does not
+/// stop the others. Surrounding whitespace is trimmed.
+pub fn add(conn: &SqliteConnection, names: &str) -> Vec<NewName> {
+    let mut seen: Vec<&str> = vec![];
+    names
+        .split(',')
+        .map(|raw| {
+            let name = raw.trim();
+            let error = if let Err(e) = clean(name) {
+                Some(e)
+            } else if seen.contains(&name) {
+                Some("Name is repeated in the input".to_string())
+            } else {
+                seen.push(name);
+                match insert_into(names::table)
+                    .values(names::name.eq(name))
+                    .execute(conn)
+                {
+                    Ok(_) => None,
+                    Err(DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+                        Some("Name exists already".to_string())
+                    }
+                    Err(e) => Some(e.to_string()),
+                }
+            };
+            NewName {
+                name: name.to_string(),
+                error,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use diesel::result::Error;
+
+    /// Names `a` and `b`, each with a photo and a visit, `b` also `bee`.
+    fn fixture() -> SqliteConnection {
+        let conn = crate::db::memory();
+        for (name, aliases) in [("a", None), ("b", Some("bee"))] {
+            insert_into(names::table)
+                .values((names::name.eq(name), names::aliases.eq(aliases)))
+                .execute(&conn)
+                .unwrap();
+            insert_into(photos::table)
+                .values((
+                    photos::filename.eq(format!("{}.jpg", name)),
+                    photos::camera_id.eq("cam"),
+                    photos::food_weight.eq(0),
+                    photos::name.eq(name),
+                ))
+                .execute(&conn)
+                .unwrap();
+            let time = NaiveDate::from_ymd_opt(2021, 5, 29)
+                .and_then(|x| x.and_hms_opt(12, 0, 0))
+                .unwrap();
+            insert_into(visits::table)
+                .values((
+                    visits::camera_id.eq("cam"),
+                    visits::start_time.eq(time),
+                    visits::end_time.eq(time),
+                    visits::weight_before.eq(0),
+                    visits::weight_after.eq(0),
+                    visits::consumed.eq(0),
+                    visits::name.eq(name),
+                ))
+                .execute(&conn)
+                .unwrap();
+        }
+        conn
+    }
+
+    fn photo_names(conn: &SqliteConnection) -> Vec<Option<String>> {
+        photos::table
+            .select(photos::name)
+            .order(photos::filename)
+            .load(conn)
+            .unwrap()
+    }
+
+    fn visit_names(conn: &SqliteConnection) -> Vec<Option<String>> {
+        visits::table
+            .select(visits::name)
+            .order(visits::id)
+            .load(conn)
+            .unwrap()
+    }
+
+    fn name_list(conn: &SqliteConnection) -> Vec<String> {
+        names::table
+            .select(names::name)
+            .order(names::name)
+            .load(conn)
+            .unwrap()
+    }
+
+    #[test]
+    fn rename_moves_references_and_keeps_the_profile() {
+        let conn = fixture();
+        assert!(rename(&conn, "b", "c").unwrap());
+        assert_eq!(name_list(&conn), vec!["a", "c"]);
+        assert_eq!(
+            photo_names(&conn),
+            vec![Some("a".to_string()), Some("c".to_string())]
+        );
+        assert_eq!(
+            visit_names(&conn),
+            vec![Some("a".to_string()), Some("c".to_string())]
+        );
+        let aliases: Option<String> = names::table
+            .find("c")
+            .select(names::aliases)
+            .first(&conn)
+            .unwrap();
+        assert_eq!(aliases.as_deref(), Some("bee"));
+        let changes = history(&conn).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            (
+                changes[0].action.as_str(),
+                changes[0].old_name.as_str(),
+                changes[0].new_name.as_str(),
+                changes[0].photos
+            ),
+            ("rename", "b", "c", 1)
+        );
+    }
+
+    #[test]
+    fn rename_refuses_missing_and_taken_names() {
+        let conn = fixture();
+        assert!(!rename(&conn, "x", "c").unwrap());
+        assert!(matches!(
+            rename(&conn, "a", "b"),
+            Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _))
+        ));
+        // Nothing changed on failure.
+        assert_eq!(name_list(&conn), vec!["a", "b"]);
+        assert!(history(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn merge_moves_references_and_unions_aliases() {
+        let conn = fixture();
+        update(names::table.find("a"))
+            .set(names::aliases.eq("ay,b"))
+            .execute(&conn)
+            .unwrap();
+        assert!(merge(&conn, "b", "a").unwrap());
+        assert_eq!(name_list(&conn), vec!["a"]);
+        assert_eq!(
+            photo_names(&conn),
+            vec![Some("a".to_string()), Some("a".to_string())]
+        );
+        assert_eq!(
+            visit_names(&conn),
+            vec![Some("a".to_string()), Some("a".to_string())]
+        );
+        let aliases: Option<String> = names::table
+            .find("a")
+            .select(names::aliases)
+            .first(&conn)
+            .unwrap();
+        let mut aliases: Vec<&str> = aliases.as_deref().unwrap().split(',').collect();
+        aliases.sort_unstable();
+        assert_eq!(aliases, vec!["ay", "b", "bee"]);
+        let changes = history(&conn).unwrap();
+        assert_eq!(
+            (
+                changes[0].action.as_str(),
+                changes[0].old_name.as_str(),
+                changes[0].new_name.as_str(),
+                changes[0].photos
+            ),
+            ("merge", "b", "a", 1)
+        );
+    }
+
+    #[test]
+    fn merge_refuses_missing_names_and_itself() {
+        let conn = fixture();
+        assert!(!merge(&conn, "x", "a").unwrap());
+        assert!(!merge(&conn, "a", "x").unwrap());
+        assert!(!merge(&conn, "a", "a").unwrap());
+        assert_eq!(name_list(&conn), vec!["a", "b"]);
+        assert!(history(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn clean_trims_and_refuses_commas() {
+        assert_eq!(clean("  Mimi "), Ok("Mimi"));
+        assert!(clean(" ").is_err());
+        assert!(clean("Mimi,Momo").is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use crate::naming;
+use crate::schema::{names, photos};
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::update;
+pub use jian_ai_api::names::{Profile, ProfileChanges};
+
+/// As the CHECK constraints of the `names` table allow.
+const SEXES: [&str; 2] = ["male", "female"];
+const STATUSES: [&str; 3] = ["active", "deceased", "adopted"];
+
+#[derive(Queryable)]
+struct ProfileRow {
+    name: String,
+    headshot: Option<String>,
+    aliases: Option<String>,
+    sex: Option<String>,
+    birth_date: Option<NaiveDate>,
+    neutered: Option<bool>,
+    description: Option<String>,
+    notes: Option<String>,
+    status: String,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "names"]
+#[changeset_options(treat_none_as_null = "true")]
+struct ProfileValues {
+    headshot: Option<String>,
+    aliases: Option<String>,
+    sex: Option<String>,
+    birth_date: Option<NaiveDate>,
+    neutered: Option<bool>,
+    description: Option<String>,
+    notes: Option<String>,
+    status: String,
+}
+
+pub fn get(conn: &SqliteConnection, name: &str) -> QueryResult<Option<Profile>> {
+    let row: Option<ProfileRow> = names::table.find(name).first(conn).optional()?;
+    row.map(|row| {
+        let seen = photos::table.filter(photos::name.eq(name));
+        let first_seen = seen
+            .select(diesel::dsl::min(photos::datetime))
+            .first(conn)?;
+        let last_seen = seen
+            .select(diesel::dsl::max(photos::datetime))
+            .first(conn)?;
+        Ok(Profile {
+            name: row.name,
+            headshot: row.headshot,
+            aliases: row
+                .aliases
+                .map(|x| x.split(',').map(|a| a.to_string()).collect())
+                .unwrap_or_default(),
+            sex: row.sex,
+            birth_date: row.birth_date,
+            neutered: row.neutered,
+            description: row.description,
+            notes: row.notes,
+            status: row.status,
+            first_seen,
+            last_seen,
+        })
+    })
+    .transpose()
+}
+
+/// What is wrong with `changes`, as the field and the problem.
+pub fn check(changes: &ProfileChanges) -> Result<(), (&'static str, String)> {
+    if let Some(sex) = changes.sex.as_deref().filter(|x| !SEXES.contains(x)) {
+        return Err((
+            "sex",
+            format!("Sex {} is not one of {}", sex, SEXES.join(", ")),
+        ));
+    }
+    if !STATUSES.contains(&changes.status.as_str()) {
+        return Err((
+            "status",
+            format!(
+                "Status {} is not one of {}",
+                changes.status,
+                STATUSES.join(", ")
+            ),
+        ));
+    }
+    for alias in changes.aliases.iter().filter(|a| !a.trim().is_empty()) {
+        naming::clean(alias).map_err(|e| ("aliases", format!("Alias {}: {}", alias.trim(), e)))?;
+    }
+    Ok(())
+}
+
+/// Returns false when there is no such name.
+pub fn change(conn: &SqliteConnection, name: &str, changes: ProfileChanges) -> QueryResult<bool> {
+    let aliases = changes
+        .aliases
+        .iter()
+        .map(|a| a.trim())
+        .filter(|a| !a.is_empty())
+        .collect::<Vec<&str>>();
+    let values = ProfileValues {
+        headshot: changes.headshot,
+        aliases: if aliases.is_empty() {
+            None
+        } else {
+            Some(aliases.join(","))
+        },
+        sex: changes.sex,
+        birth_date: changes.birth_date,
+        neutered: changes.neutered,
+        description: changes.description,
+        notes: changes.notes,
+        status: changes.status,
+    };
+    Ok(update(names::table.find(name)).set(&values).execute(conn)? > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(sex: Option<&str>, status: &str) -> ProfileChanges {
+        ProfileChanges {
+            headshot: None,
+            aliases: vec![],
+            sex: sex.map(str::to_string),
+            birth_date: None,
+            neutered: None,
+            description: None,
+            notes: None,
+            status: status.to_string(),
+        }
+    }
+
+    #[test]
+    fn check_names_the_bad_field() {
+        assert!(check(&changes(Some("female"), "adopted")).is_ok());
+        assert!(check(&changes(None, "active")).is_ok());
+        assert_eq!(check(&changes(Some("cat"), "active")).unwrap_err().0, "sex");
+        assert_eq!(check(&changes(None, "lost")).unwrap_err().0, "status");
+    }
+}
@@ -0,0 +1,194 @@
+use crate::schema::photos;
+use crate::visits;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::update;
+pub use jian_ai_api::photos::{Filter, Order, Page, Photo};
+
+/// `photos` columns in the order of `Photo`.
+type Columns = (
+    photos::filename,
+    photos::datetime,
+    photos::camera_id,
+    photos::food_weight,
+    photos::name,
+    photos::confidence,
+    photos::width,
+    photos::height,
+    photos::visit_id,
+);
+
+const COLUMNS: Columns = (
+    photos::filename,
+    photos::datetime,
+    photos::camera_id,
+    photos::food_weight,
+    photos::name,
+    photos::confidence,
+    photos::width,
+    photos::height,
+    photos::visit_id,
+);
+
+/// Photos nobody is named in, oldest first.
+pub fn unnamed(conn: &SqliteConnection) -> QueryResult<Vec<Photo>> {
+    photos::table
+        .select(COLUMNS)
+        .filter(photos::name.is_null())
+        .order((photos::datetime, photos::filename))
+        .load(conn)
+}
+
+/// Up to `limit` photos `filter` lets through, following the photo named
+/// `after` in `order`. Fails with `NotFound` when there is no such photo.
+pub fn list(
+    conn: &SqliteConnection,
+    filter: &Filter,
+    order: Order,
+    after: Option<&str>,
+    limit: i64,
+) -> QueryResult<Page> {
+    let mut query = photos::table.select(COLUMNS).into_boxed();
+    if let Some(camera_id) = &filter.camera_id {
+        query = query.filter(photos::camera_id.eq(camera_id));
+    }
+    if let Some(name) = &filter.name {
+        query = query.filter(photos::name.eq(name));
+    }
+    match filter.named {
+        Some(true) => query = query.filter(photos::name.is_not_null()),
+        Some(false) => query = query.filter(photos::name.is_null()),
+        None => {}
+    }
+    if let Some(since) = filter.since {
+        query = query.filter(photos::datetime.ge(since));
+    }
+    if let Some(until) = filter.until {
+        query = query.filter(photos::datetime.lt(until));
+    }
+    if let Some(min_weight) = filter.min_weight {
+        query = query.filter(photos::food_weight.ge(min_weight));
+    }
+    if let Some(max_weight) = filter.max_weight {
+        query = query.filter(photos::food_weight.le(max_weight));
+    }
+    // Keyset paging: carry on after (datetime, filename) of the last photo.
+    // SQLite sorts photos without a time before all others.
+    if let Some(after) = after {
+        let datetime: Option<NaiveDateTime> = photos::table
+            .find(after)
+            .select(photos::datetime)
+            .first(conn)?;
+        query = match (order, datetime) {
+            (Order::OldestFirst, Some(datetime)) => query.filter(
+                photos::datetime.gt(datetime).or(photos::datetime
+                    .eq(datetime)
+                    .and(photos::filename.gt(after))),
+            ),
+            (Order::OldestFirst, None) => query.filter(
+                photos::datetime
+                    .is_not_null()
+                    .or(photos::filename.gt(after)),
+            ),
+            (Order::NewestFirst, Some(datetime)) => query.filter(
+                photos::datetime
+                    .lt(datetime)
+                    .or(photos::datetime
+                        .eq(datetime)
+                        .and(photos::filename.lt(after)))
+                    .or(photos::datetime.is_null()),
+            ),
+            (Order::NewestFirst, None) => {
+                query.filter(photos::datetime.is_null().and(photos::filename.lt(after)))
+            }
+        };
+    }
+    query = match order {
+        Order::OldestFirst => query.order((photos::datetime.asc(), photos::filename.asc())),
+        Order::NewestFirst => query.order((photos::datetime.desc(), photos::filename.desc())),
+    };
+    // One more than asked tells whether another page follows.
+    let mut photos: Vec<Photo> = query.limit(limit + 1).load(conn)?;
+    let next = if photos.len() as i64 > limit {
+        photos.truncate(limit as usize);
+        photos.last().map(|x| x.filename.clone())
+    } else {
+        None
+    };
+    Ok(Page { photos, next })
+}
+
+/// Name the photo by hand, or unname it with `None`, and rename its visit to
+/// match. Returns false when there is no such photo.
+pub fn name(conn: &SqliteConnection, filename: &str, name: Option<&str>) -> QueryResult<bool> {
+    conn.transaction(|| {
+        let visit_id: Option<Option<i32>> = photos::table
+            .find(filename)
+            .select(photos::visit_id)
+            .first(conn)
+            .optional()?;
+        let visit_id = match visit_id {
+            Some(x) => x,
+            None => return Ok(false),
+        };
+        update(photos::table.find(filename))
+            .set((photos::name.eq(name), photos::confidence.eq(None::<f32>)))
+            .execute(conn)?;
+        if let Some(visit_id) = visit_id {
+            visits::name_from_photos(conn, visit_id)?;
+        }
+        Ok(true)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use diesel::insert_into;
+
+    fn walk(conn: &SqliteConnection, order: Order, limit: i64) -> Vec<String> {
+        let mut seen = vec![];
+        let mut after = None;
+        loop {
+            let page = list(conn, &Filter::default(), order, after.as_deref(), limit).unwrap();
+            seen.extend(page.photos.into_iter().map(|x| x.filename));
+            match page.next {
+                Some(next) => after = Some(next),
+                None => return seen,
+            }
+        }
+    }
+
+    #[test]
+    fn pages_skip_and_repeat_nothing() {
+        let conn = crate::db::memory();
+        let at =
+            |hour| NaiveDate::from_ymd_opt(2021, 5, 29).and_then(|x| x.and_hms_opt(hour, 0, 0));
+        for (filename, datetime) in [
+            ("e2", at(12)),
+            ("n2", None),
+            ("l2", at(13)),
+            ("e1", at(12)),
+            ("l1", at(11)),
+            ("n1", None),
+            ("e3", at(12)),
+        ] {
+            insert_into(photos::table)
+                .values((
+                    photos::filename.eq(filename),
+                    photos::camera_id.eq("cam"),
+                    photos::datetime.eq(datetime),
+                    photos::food_weight.eq(0),
+                ))
+                .execute(&conn)
+                .unwrap();
+        }
+        let oldest = vec!["n1", "n2", "l1", "e1", "e2", "e3", "l2"];
+        let newest: Vec<&str> = oldest.iter().rev().copied().collect();
+        for limit in 1..=4 {
+            assert_eq!(walk(&conn, Order::OldestFirst, limit), oldest);
+            assert_eq!(walk(&conn, Order::NewestFirst, limit), newest);
+        }
+    }
+}
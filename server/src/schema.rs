@@ -1,6 +1,38 @@
+table! {
+    cameras (id) {
+        id -> Text,
+        display_name -> Nullable<Text>,
+        location -> Nullable<Text>,
+        feeder_capacity -> Nullable<SmallInt>,
+        timezone -> Nullable<Text>,
+        last_seen -> Nullable<Timestamp>,
+        firmware -> Nullable<Text>,
+        retired -> Bool,
+    }
+}
+
+table! {
+    name_changes (id) {
+        id -> Integer,
+        datetime -> Timestamp,
+        action -> Text,
+        old_name -> Text,
+        new_name -> Text,
+        photos -> Integer,
+    }
+}
+
 table! {
     names (name) {
         name -> Text,
+        headshot -> Nullable<Text>,
+        aliases -> Nullable<Text>,
+        sex -> Nullable<Text>,
+        birth_date -> Nullable<Date>,
+        neutered -> Nullable<Bool>,
+        description -> Nullable<Text>,
+        notes -> Nullable<Text>,
+        status -> Text,
     }
 }
 
@@ -11,12 +43,39 @@ table! {
         camera_id -> Text,
         food_weight -> SmallInt,
         name -> Nullable<Text>,
+        confidence -> Nullable<Float>,
+        visit_id -> Nullable<Integer>,
+        datetime_source -> Nullable<Text>,
+        content_hash -> Nullable<Text>,
+        perceptual_hash -> Nullable<BigInt>,
+        width -> Nullable<Integer>,
+        height -> Nullable<Integer>,
+    }
+}
+
+table! {
+    visits (id) {
+        id -> Integer,
+        camera_id -> Text,
+        start_time -> Timestamp,
+        end_time -> Timestamp,
+        weight_before -> SmallInt,
+        weight_after -> SmallInt,
+        consumed -> SmallInt,
+        name -> Nullable<Text>,
     }
 }
 
+joinable!(photos -> cameras (camera_id));
 joinable!(photos -> names (name));
+joinable!(photos -> visits (visit_id));
+joinable!(visits -> cameras (camera_id));
+joinable!(visits -> names (name));
 
 allow_tables_to_appear_in_same_query!(
+    cameras,
+    name_changes,
     names,
     photos,
+    visits,
 );
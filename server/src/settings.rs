@@ -0,0 +1,99 @@
+use crate::storage::{LocalStore, PhotoStore, S3Store};
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreKind {
+    Local,
+    S3,
+}
+
+/// What the server needs beside Rocket's own settings (address, port,
+/// databases). Read from `[global]` in Rocket.toml, each key can be
+/// overridden by a `ROCKET_<KEY>` environment variable.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Directory of the built `webpages`, relative to Rocket.toml.
+    pub frontend: PathBuf,
+    pub photo_store: StoreKind,
+    /// `local`: directory of photos, relative to Rocket.toml.
+    pub pics: PathBuf,
+    /// `local`: spread photos over `<yyyy>/<mm>/<dd>/<camera>/` directories,
+    /// photos stored flat before are still found.
+    pub pics_sharded: bool,
+    pub s3_bucket: Option<String>,
+    pub s3_region: String,
+    /// `s3`: URL of an S3 compatible server like MinIO, AWS when missing.
+    pub s3_endpoint: Option<String>,
+    pub s3_access_key: Option<String>,
+    pub s3_secret_key: Option<String>,
+    /// In bytes, bigger uploads are refused.
+    pub max_upload: u64,
+    /// Automatic names less certain than this are left for manual tagging.
+    pub identify_threshold: f32,
+    /// Most similar named photos voting in identifying.
+    pub identify_k: usize,
+    /// Share of how often a name shows up at the camera in identifying, the
+    /// rest is the visual vote.
+    pub identify_camera_weight: f32,
+    /// Photos of a camera further apart than this are different visits.
+    pub visit_gap_minutes: i64,
+    /// How long a query waits for another connection's write to finish.
+    pub busy_timeout_ms: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            frontend: PathBuf::from("../webpages/dist"),
+            photo_store: StoreKind::Local,
+            pics: PathBuf::from("pics"),
+            pics_sharded: true,
+            s3_bucket: None,
+            s3_region: "us-east-1".to_string(),
+            s3_endpoint: None,
+            s3_access_key: None,
+            s3_secret_key: None,
+            max_upload: 20 * 1024 * 1024,
+            identify_threshold: 0.8,
+            identify_k: 5,
+            identify_camera_weight: 0.2,
+            visit_gap_minutes: 5,
+            busy_timeout_ms: 5000,
+        }
+    }
+}
+
+impl Settings {
+    pub fn visit_gap(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.visit_gap_minutes)
+    }
+
+    pub fn photo_store(&self) -> Result<Arc<dyn PhotoStore>, Box<dyn std::error::Error>> {
+        match self.photo_store {
+            StoreKind::Local => Ok(Arc::new(LocalStore::new(
+                self.pics.clone(),
+                self.pics_sharded,
+            )?)),
+            StoreKind::S3 => {
+                let missing = |key: &str| format!("{} is required for the s3 photo_store", key);
+                Ok(Arc::new(S3Store::new(
+                    self.s3_bucket
+                        .as_deref()
+                        .ok_or_else(|| missing("s3_bucket"))?,
+                    &self.s3_region,
+                    self.s3_endpoint.as_deref(),
+                    self.s3_access_key
+                        .as_deref()
+                        .ok_or_else(|| missing("s3_access_key"))?,
+                    self.s3_secret_key
+                        .as_deref()
+                        .ok_or_else(|| missing("s3_secret_key"))?,
+                )?))
+            }
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use crate::schema::{cameras, photos, visits};
+use chrono::{NaiveDateTime, TimeZone, Timelike};
+use chrono_tz::Tz;
+use diesel::prelude::*;
+pub use jian_ai_api::stats::{Consumption, Count, HourlyActivity, Window};
+use std::collections::{BTreeMap, HashMap};
+
+/// Timezones of the cameras, so days and hours are those at the feeder.
+/// Cameras without a known timezone stay in UTC.
+struct LocalTime(HashMap<String, Tz>);
+
+impl LocalTime {
+    fn load(conn: &SqliteConnection) -> QueryResult<Self> {
+        let rows: Vec<(String, Option<String>)> = cameras::table
+            .select((cameras::id, cameras::timezone))
+            .load(conn)?;
+        Ok(LocalTime(
+            rows.into_iter()
+                .filter_map(|(id, timezone)| Some((id, timezone?.parse().ok()?)))
+                .collect(),
+        ))
+    }
+
+    /// `datetime`, in UTC, on the clock at the camera.
+    fn at(&self, camera_id: &str, datetime: NaiveDateTime) -> NaiveDateTime {
+        match self.0.get(camera_id) {
+            Some(tz) => tz.from_utc_datetime(&datetime).naive_local(),
+            None => datetime,
+        }
+    }
+}
+
+/// Food eaten between a photo and the previous one of the same camera,
+/// credited to the later photo.
+struct Meal {
+    camera_id: String,
+    /// Local to the camera.
+    datetime: NaiveDateTime,
+    name: Option<String>,
+    consumed: i64,
+}
+
+fn meals(conn: &SqliteConnection) -> QueryResult<Vec<Meal>> {
+    let rows: Vec<(String, Option<NaiveDateTime>, i16, Option<String>)> = photos::table
+        .select((
+            photos::camera_id,
+            photos::datetime,
+            photos::food_weight,
+            photos::name,
+        ))
+        .filter(photos::datetime.is_not_null())
+        .order((photos::camera_id, photos::datetime))
+        .load(conn)?;
+    let local = LocalTime::load(conn)?;
+    Ok(rows
+        .windows(2)
+        .filter_map(|pair| {
+            let (prev_camera, _, prev_weight, _) = &pair[0];
+            let (camera_id, datetime, weight, name) = &pair[1];
+            let consumed = *prev_weight as i64 - *weight as i64;
+            // A raise in weight is a refill, not a meal.
+            if prev_camera != camera_id || consumed <= 0 {
+                return None;
+            }
+            Some(Meal {
+                camera_id: camera_id.clone(),
+                datetime: local.at(camera_id, (*datetime)?),
+                name: name.clone(),
+                consumed,
+            })
+        })
+        .collect())
+}
+
+fn sum_by<F>(meals: Vec<Meal>, window: Window, key: F) -> Vec<Consumption>
+where
+    F: Fn(&Meal) -> Option<String>,
+{
+    let mut sums: BTreeMap<(String, String), i64> = BTreeMap::new();
+    for meal in meals {
+        if let Some(k) = key(&meal) {
+            *sums.entry((k, window.period(meal.datetime))).or_default() += meal.consumed;
+        }
+    }
+    sums.into_iter()
+        .map(|((key, period), consumed)| Consumption {
+            key,
+            period,
+            consumed,
+        })
+        .collect()
+}
+
+/// Food eaten by each named animal per period. Meals on unnamed photos are left out.
+pub fn by_name(conn: &SqliteConnection, window: Window) -> QueryResult<Vec<Consumption>> {
+    Ok(sum_by(meals(conn)?, window, |m| m.name.clone()))
+}
+
+/// Food gone from each camera's feeder per period.
+pub fn by_camera(conn: &SqliteConnection, window: Window) -> QueryResult<Vec<Consumption>> {
+    Ok(sum_by(meals(conn)?, window, |m| Some(m.camera_id.clone())))
+}
+
+/// Camera, name and start time, in UTC, of every named visit.
+fn named_visits(conn: &SqliteConnection) -> QueryResult<Vec<(String, String, NaiveDateTime)>> {
+    let rows: Vec<(String, Option<String>, NaiveDateTime)> = visits::table
+        .select((visits::camera_id, visits::name, visits::start_time))
+        .filter(visits::name.is_not_null())
+        .load(conn)?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(camera_id, name, start_time)| Some((camera_id, name?, start_time)))
+        .collect())
+}
+
+/// Number of visits of each named animal per period.
+pub fn visits_by_name(conn: &SqliteConnection, window: Window) -> QueryResult<Vec<Count>> {
+    let local = LocalTime::load(conn)?;
+    let mut counts: BTreeMap<(String, String), i64> = BTreeMap::new();
+    for (camera_id, name, start_time) in named_visits(conn)? {
+        let period = window.period(local.at(&camera_id, start_time));
+        *counts.entry((name, period)).or_default() += 1;
+    }
+    Ok(counts
+        .into_iter()
+        .map(|((key, period), count)| Count { key, period, count })
+        .collect())
+}
+
+/// When during the day each named animal shows up.
+pub fn hourly_activity(conn: &SqliteConnection) -> QueryResult<Vec<HourlyActivity>> {
+    let local = LocalTime::load(conn)?;
+    let mut activities: BTreeMap<String, [i64; 24]> = BTreeMap::new();
+    for (camera_id, name, start_time) in named_visits(conn)? {
+        let hour = local.at(&camera_id, start_time).hour() as usize;
+        activities.entry(name).or_insert([0; 24])[hour] += 1;
+    }
+    Ok(activities
+        .into_iter()
+        .map(|(name, visits)| HourlyActivity { name, visits })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::names;
+    use chrono::NaiveDate;
+    use diesel::insert_into;
+
+    #[test]
+    fn hours_and_days_are_local_to_the_camera() {
+        let conn = crate::db::memory();
+        insert_into(cameras::table)
+            .values((cameras::id.eq("sh"), cameras::timezone.eq("Asia/Shanghai")))
+            .execute(&conn)
+            .unwrap();
+        insert_into(cameras::table)
+            .values(cameras::id.eq("utc"))
+            .execute(&conn)
+            .unwrap();
+        // 20:30 UTC is 04:30 the next day in Shanghai.
+        let start = NaiveDate::from_ymd_opt(2021, 5, 29)
+            .and_then(|x| x.and_hms_opt(20, 30, 0))
+            .unwrap();
+        for camera_id in ["sh", "utc"] {
+            insert_into(names::table)
+                .values(names::name.eq(camera_id))
+                .execute(&conn)
+                .unwrap();
+            insert_into(visits::table)
+                .values((
+                    visits::camera_id.eq(camera_id),
+                    visits::start_time.eq(start),
+                    visits::end_time.eq(start),
+                    visits::weight_before.eq(0),
+                    visits::weight_after.eq(0),
+                    visits::consumed.eq(0),
+                    visits::name.eq(camera_id),
+                ))
+                .execute(&conn)
+                .unwrap();
+        }
+        let hours: Vec<(String, usize)> = hourly_activity(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|x| (x.name, x.visits.iter().position(|v| *v == 1).unwrap()))
+            .collect();
+        assert_eq!(hours, vec![("sh".to_string(), 4), ("utc".to_string(), 20)]);
+        let days: Vec<(String, String)> = visits_by_name(&conn, Window::Day)
+            .unwrap()
+            .into_iter()
+            .map(|x| (x.key, x.period))
+            .collect();
+        assert_eq!(
+            days,
+            vec![
+                ("sh".to_string(), "2021-05-30".to_string()),
+                ("utc".to_string(), "2021-05-29".to_string()),
+            ]
+        );
+    }
+}
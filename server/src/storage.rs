@@ -0,0 +1,240 @@
+use s3::creds::Credentials;
+use s3::{Bucket, Region};
+use std::path::{Component, Path, PathBuf};
+
+/// Where photo bytes live. Keys are photo filenames, optionally under
+/// `/` separated prefixes like `thumbs/160/`.
+pub trait PhotoStore: Send + Sync {
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), Box<dyn std::error::Error>>;
+    /// `None` when there is no such photo.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>>;
+    fn delete(&self, key: &str) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// Photos in a directory. When sharded, photos go to
+/// `<prefix>/<yyyy>/<mm>/<dd>/<camera>/<filename>` by the camera and upload
+/// date in their filenames, so no directory grows without bound. Photos
+/// stored flat before sharding are still found.
+pub struct LocalStore {
+    root: PathBuf,
+    sharded: bool,
+}
+
+impl LocalStore {
+    pub fn new(root: PathBuf, sharded: bool) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&root)?;
+        Ok(LocalStore { root, sharded })
+    }
+
+    /// `None` for keys reaching out of the root.
+    fn flat(&self, key: &str) -> Option<PathBuf> {
+        let key = Path::new(key);
+        if key.as_os_str().is_empty()
+            || key.components().any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return None;
+        }
+        Some(self.root.join(key))
+    }
+
+    fn shard(&self, key: &str) -> Option<PathBuf> {
+        let flat = self.flat(key)?;
+        let filename = flat.file_name()?.to_str()?;
+        // `<camera>-<yyyymmdd>_<hhmmss>_<nanos>.<ext>`, camera ids may have `-` too.
+        let (camera, time) = filename.rsplit_once('-')?;
+        let date = time
+            .get(..8)
+            .filter(|x| x.bytes().all(|b| b.is_ascii_digit()))?;
+        Some(
+            flat.parent()?
+                .join(&date[..4])
+                .join(&date[4..6])
+                .join(&date[6..])
+                .join(camera)
+                .join(filename),
+        )
+    }
+
+    /// Candidates to read, the preferred one first.
+    fn paths(&self, key: &str) -> Vec<PathBuf> {
+        let mut paths = vec![];
+        if self.sharded {
+            paths.extend(self.shard(key));
+        }
+        paths.extend(self.flat(key));
+        paths
+    }
+}
+
+impl PhotoStore for LocalStore {
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        let path = self
+            .paths(key)
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("Invalid photo key {}", key))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        for path in self.paths(key) {
+            match std::fs::read(path) {
+                Ok(bytes) => return Ok(Some(bytes)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(None)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), Box<dyn std::error::Error>> {
+        for path in self.paths(key) {
+            match std::fs::remove_file(path) {
+                Ok(_) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Photos in an S3 bucket, keys as they are.
+pub struct S3Store {
+    bucket: Box<Bucket>,
+}
+
+impl S3Store {
+    /// With an `endpoint`, talks path style to that server, as MinIO and
+    /// other S3 compatible stores want, otherwise to AWS in `region`.
+    pub fn new(
+        bucket: &str,
+        region: &str,
+        endpoint: Option<&str>,
+        access_key: &str,
+        secret_key: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let credentials = Credentials::new(Some(access_key), Some(secret_key), None, None, None)?;
+        let bucket = match endpoint {
+            Some(endpoint) => Bucket::new(
+                bucket,
+                Region::Custom {
+                    region: region.to_string(),
+                    endpoint: endpoint.to_string(),
+                },
+                credentials,
+            )?
+            .with_path_style(),
+            None => Bucket::new(bucket, region.parse()?, credentials)?,
+        };
+        Ok(S3Store { bucket })
+    }
+}
+
+fn check(status: u16, key: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if (200..300).contains(&status) {
+        Ok(())
+    } else {
+        Err(format!("S3 answered {} for {}", status, key).into())
+    }
+}
+
+impl PhotoStore for S3Store {
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        check(self.bucket.put_object(key, bytes)?.status_code(), key)
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        let response = self.bucket.get_object(key)?;
+        if response.status_code() == 404 {
+            return Ok(None);
+        }
+        check(response.status_code(), key)?;
+        Ok(Some(response.bytes().to_vec()))
+    }
+
+    fn delete(&self, key: &str) -> Result<(), Box<dyn std::error::Error>> {
+        check(self.bucket.delete_object(key)?.status_code(), key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHOTO: &str = "cam-1-20240102_030405_6.jpg";
+
+    #[test]
+    fn shards_by_date_and_camera() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path().to_path_buf(), true).unwrap();
+        assert_eq!(
+            store.shard(PHOTO),
+            Some(dir.path().join("2024/01/02/cam-1").join(PHOTO))
+        );
+        assert_eq!(
+            store.shard(&format!("thumbs/160/{}", PHOTO)),
+            Some(dir.path().join("thumbs/160/2024/01/02/cam-1").join(PHOTO))
+        );
+        assert_eq!(store.shard("cam-1-today.jpg"), None);
+        assert_eq!(store.shard("../x-20240102_030405_6.jpg"), None);
+    }
+
+    #[test]
+    fn finds_flat_photos_once_sharded() {
+        let dir = tempfile::tempdir().unwrap();
+        let flat = LocalStore::new(dir.path().to_path_buf(), false).unwrap();
+        flat.put(PHOTO, b"before").unwrap();
+        assert!(dir.path().join(PHOTO).is_file());
+
+        let sharded = LocalStore::new(dir.path().to_path_buf(), true).unwrap();
+        assert_eq!(sharded.get(PHOTO).unwrap(), Some(b"before".to_vec()));
+        sharded
+            .put("cam-1-20240102_030405_7.jpg", b"after")
+            .unwrap();
+        assert!(dir
+            .path()
+            .join("2024/01/02/cam-1/cam-1-20240102_030405_7.jpg")
+            .is_file());
+
+        sharded.delete(PHOTO).unwrap();
+        assert_eq!(sharded.get(PHOTO).unwrap(), None);
+        assert!(!dir.path().join(PHOTO).exists());
+    }
+
+    #[test]
+    fn refuses_keys_out_of_the_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path().join("photos"), true).unwrap();
+        assert!(store.put("../escaped.jpg", b"x").is_err());
+        assert!(store.put("", b"x").is_err());
+        assert_eq!(store.get("/etc/passwd").unwrap(), None);
+    }
+
+    /// Against a MinIO or other S3 compatible server, configured by the
+    /// `S3_ENDPOINT`, `S3_BUCKET`, `S3_ACCESS_KEY` and `S3_SECRET_KEY`
+    /// environment variables, the bucket already there. Run with
+    /// `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn s3_round_trip() {
+        let var = |name: &str, default: &str| std::env::var(name).unwrap_or(default.to_string());
+        let store = S3Store::new(
+            &var("S3_BUCKET", "jian-ai"),
+            "us-east-1",
+            Some(&var("S3_ENDPOINT", "http://localhost:9000")),
+            &var("S3_ACCESS_KEY", "minioadmin"),
+            &var("S3_SECRET_KEY", "minioadmin"),
+        )
+        .unwrap();
+        let key = format!("thumbs/160/{}", PHOTO);
+        store.put(&key, b"photo").unwrap();
+        assert_eq!(store.get(&key).unwrap(), Some(b"photo".to_vec()));
+        store.delete(&key).unwrap();
+        assert_eq!(store.get(&key).unwrap(), None);
+    }
+}
@@ -0,0 +1,317 @@
+use crate::schema::{photos, visits};
+use chrono::{Duration, NaiveDateTime};
+use diesel::prelude::*;
+use diesel::{insert_into, update};
+pub use jian_ai_api::visits::Visit;
+use std::collections::HashMap;
+
+no_arg_sql_function!(
+    last_insert_rowid,
+    diesel::sql_types::Integer,
+    "Row id of the last insertion on this connection."
+);
+
+/// A photo waiting to be put into a visit.
+#[derive(Queryable)]
+struct Ungrouped {
+    filename: String,
+    camera_id: String,
+    datetime: Option<NaiveDateTime>,
+    food_weight: i16,
+}
+
+/// The name is left to `name_from_photos`.
+#[derive(Insertable, AsChangeset)]
+#[table_name = "visits"]
+struct VisitValues {
+    camera_id: String,
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+    weight_before: i16,
+    weight_after: i16,
+    consumed: i16,
+}
+
+impl VisitValues {
+    fn admits(&self, datetime: NaiveDateTime, gap: Duration) -> bool {
+        self.start_time - gap <= datetime && datetime <= self.end_time + gap
+    }
+
+    fn add(&mut self, datetime: NaiveDateTime, food_weight: i16) {
+        if datetime < self.start_time {
+            self.start_time = datetime;
+            self.weight_before = food_weight;
+        }
+        if datetime > self.end_time {
+            self.end_time = datetime;
+            self.weight_after = food_weight;
+        }
+        self.consumed = (self.weight_before - self.weight_after).max(0);
+    }
+}
+
+/// Put every photo not in a visit yet into one, either extending the
+/// camera's latest visit or starting a new one when photos are more than
+/// `gap` apart. Returns how many photos got grouped.
+pub fn group(conn: &SqliteConnection, gap: Duration) -> QueryResult<usize> {
+    conn.transaction(|| {
+        let pending: Vec<Ungrouped> = photos::table
+            .select((
+                photos::filename,
+                photos::camera_id,
+                photos::datetime,
+                photos::food_weight,
+            ))
+            .filter(photos::visit_id.is_null())
+            .filter(photos::datetime.is_not_null())
+            .order((photos::camera_id, photos::datetime))
+            .load(conn)?;
+        let grouped = pending.len();
+
+        let mut current: Option<(i32, VisitValues)> = None;
+        for Ungrouped {
+            filename,
+            camera_id,
+            datetime,
+            food_weight,
+        } in pending
+        {
+            let datetime = datetime.expect("Filtered by is_not_null");
+            if current.as_ref().map(|(_, v)| &v.camera_id) != Some(&camera_id) {
+                flush(conn, current.take())?;
+                current = visits::table
+                    .filter(visits::camera_id.eq(&camera_id))
+                    .order(visits::end_time.desc())
+                    .first::<Visit>(conn)
+                    .optional()?
+                    .map(|v| {
+                        (
+                            v.id,
+                            VisitValues {
+                                camera_id: v.camera_id,
+                                start_time: v.start_time,
+                                end_time: v.end_time,
+                                weight_before: v.weight_before,
+                                weight_after: v.weight_after,
+                                consumed: v.consumed,
+                            },
+                        )
+                    });
+            }
+            match &mut current {
+                Some((_, visit)) if visit.admits(datetime, gap) => visit.add(datetime, food_weight),
+                _ => {
+                    flush(conn, current.take())?;
+                    let visit = VisitValues {
+                        camera_id,
+                        start_time: datetime,
+                        end_time: datetime,
+                        weight_before: food_weight,
+                        weight_after: food_weight,
+                        consumed: 0,
+                    };
+                    insert_into(visits::table).values(&visit).execute(conn)?;
+                    let id = diesel::select(last_insert_rowid).get_result(conn)?;
+                    current = Some((id, visit));
+                }
+            }
+            let id = current.as_ref().map(|(id, _)| *id);
+            update(photos::table.filter(photos::filename.eq(filename)))
+                .set(photos::visit_id.eq(id))
+                .execute(conn)?;
+        }
+        flush(conn, current)?;
+        Ok(grouped)
+    })
+}
+
+fn flush(conn: &SqliteConnection, visit: Option<(i32, VisitValues)>) -> QueryResult<()> {
+    if let Some((id, values)) = visit {
+        update(visits::table.find(id)).set(&values).execute(conn)?;
+        name_from_photos(conn, id)?;
+    }
+    Ok(())
+}
+
+/// Name the visit like most of its photos, ties going to the name sorting
+/// first. Unnamed when none of its photos is named.
+pub fn name_from_photos(conn: &SqliteConnection, visit_id: i32) -> QueryResult<()> {
+    let names: Vec<Option<String>> = photos::table
+        .select(photos::name)
+        .filter(photos::visit_id.eq(visit_id))
+        .filter(photos::name.is_not_null())
+        .load(conn)?;
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for name in names.into_iter().flatten() {
+        *counts.entry(name).or_insert(0) += 1;
+    }
+    let name = counts
+        .into_iter()
+        .max_by(|(a, x), (b, y)| x.cmp(y).then(b.cmp(a)))
+        .map(|(name, _)| name);
+    update(visits::table.find(visit_id))
+        .set(visits::name.eq(name))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Name a visit together with all of its photos, `false` when there is no
+/// such visit.
+pub fn name(conn: &SqliteConnection, visit_id: i32, name: &str) -> QueryResult<bool> {
+    conn.transaction(|| {
+        let found = update(visits::table.find(visit_id))
+            .set(visits::name.eq(Some(name)))
+            .execute(conn)?
+            > 0;
+        update(photos::table.filter(photos::visit_id.eq(visit_id)))
+            .set((
+                photos::name.eq(Some(name)),
+                photos::confidence.eq(None::<f32>),
+            ))
+            .execute(conn)?;
+        Ok(found)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::names;
+    use chrono::NaiveDate;
+
+    fn at(minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2021, 5, 29)
+            .and_then(|x| x.and_hms_opt(12, minute, 0))
+            .unwrap()
+    }
+
+    fn visit(start: u32, end: u32) -> VisitValues {
+        VisitValues {
+            camera_id: "a".to_string(),
+            start_time: at(start),
+            end_time: at(end),
+            weight_before: 100,
+            weight_after: 90,
+            consumed: 10,
+        }
+    }
+
+    #[test]
+    fn admits_photos_within_gap() {
+        let visit = visit(10, 20);
+        let gap = Duration::minutes(5);
+        assert!(visit.admits(at(5), gap));
+        assert!(visit.admits(at(15), gap));
+        assert!(visit.admits(at(25), gap));
+        assert!(!visit.admits(at(4), gap));
+        assert!(!visit.admits(at(26), gap));
+    }
+
+    #[test]
+    fn add_moves_weights_with_the_ends() {
+        let mut visit = visit(10, 20);
+        visit.add(at(8), 120);
+        assert_eq!((visit.start_time, visit.weight_before), (at(8), 120));
+        assert_eq!(visit.consumed, 30);
+        visit.add(at(22), 70);
+        assert_eq!((visit.end_time, visit.weight_after), (at(22), 70));
+        assert_eq!(visit.consumed, 50);
+        // Photos in between change neither end.
+        visit.add(at(15), 10);
+        assert_eq!((visit.weight_before, visit.weight_after), (120, 70));
+    }
+
+    #[test]
+    fn refill_counts_as_nothing_eaten() {
+        let mut visit = visit(10, 20);
+        visit.add(at(25), 200);
+        assert_eq!(visit.consumed, 0);
+    }
+
+    #[test]
+    fn group_splits_at_gaps() {
+        let conn = crate::db::memory();
+        for (filename, camera_id, minute, food_weight) in [
+            ("a1", "a", 0, 100),
+            ("a2", "a", 3, 95),
+            ("a3", "a", 30, 95),
+            ("a4", "a", 32, 120),
+            ("b1", "b", 1, 50),
+        ] {
+            insert_into(photos::table)
+                .values((
+                    photos::filename.eq(filename),
+                    photos::camera_id.eq(camera_id),
+                    photos::datetime.eq(at(minute)),
+                    photos::food_weight.eq(food_weight as i16),
+                ))
+                .execute(&conn)
+                .unwrap();
+        }
+        assert_eq!(group(&conn, Duration::minutes(5)).unwrap(), 5);
+        let visits: Vec<Visit> = visits::table
+            .order((visits::camera_id, visits::start_time))
+            .load(&conn)
+            .unwrap();
+        let summary: Vec<(&str, i16, i16, i16)> = visits
+            .iter()
+            .map(|v| {
+                (
+                    v.camera_id.as_str(),
+                    v.weight_before,
+                    v.weight_after,
+                    v.consumed,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("a", 100, 95, 5), ("a", 95, 120, 0), ("b", 50, 50, 0)]
+        );
+        // Everything is grouped already.
+        assert_eq!(group(&conn, Duration::minutes(5)).unwrap(), 0);
+    }
+
+    #[test]
+    fn visit_is_named_like_most_photos() {
+        let conn = crate::db::memory();
+        for name in ["mimi", "huahua"] {
+            insert_into(names::table)
+                .values(names::name.eq(name))
+                .execute(&conn)
+                .unwrap();
+        }
+        for (filename, name) in [
+            ("p1", Some("mimi")),
+            ("p2", Some("huahua")),
+            ("p3", Some("mimi")),
+            ("p4", None),
+        ] {
+            insert_into(photos::table)
+                .values((
+                    photos::filename.eq(filename),
+                    photos::camera_id.eq("a"),
+                    photos::datetime.eq(at(0)),
+                    photos::food_weight.eq(100),
+                    photos::name.eq(name),
+                ))
+                .execute(&conn)
+                .unwrap();
+        }
+        group(&conn, Duration::minutes(5)).unwrap();
+        let name = || {
+            visits::table
+                .select(visits::name)
+                .first::<Option<String>>(&conn)
+                .unwrap()
+        };
+        assert_eq!(name().as_deref(), Some("mimi"));
+        update(photos::table)
+            .set(photos::name.eq(None::<String>))
+            .execute(&conn)
+            .unwrap();
+        let visit_id = visits::table.select(visits::id).first(&conn).unwrap();
+        name_from_photos(&conn, visit_id).unwrap();
+        assert_eq!(name(), None);
+    }
+}
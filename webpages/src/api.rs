@@ -0,0 +1,43 @@
+use anyhow::{anyhow, Result};
+use jian_ai_api::error::ErrorBody;
+use jian_ai_api::DeserializeOwned;
+use reqwasm::http::{Request, Response};
+
+/// Where the server is: `JIAN_AI_SERVER` at build time, like
+/// `http://localhost:8000` when the pages are served by `trunk serve`,
+/// otherwise the origin the pages are served from.
+pub fn base_url() -> String {
+    match option_env!("JIAN_AI_SERVER") {
+        Some(server) => server.trim_end_matches('/').to_string(),
+        None => web_sys::window()
+            .and_then(|w| w.location().origin().ok())
+            .unwrap_or_default(),
+    }
+}
+
+/// `path` on the server, starting with `/`, like the paths of `jian_ai_api`.
+pub fn url(path: &str) -> String {
+    format!("{}{}", base_url(), path)
+}
+
+/// Send `request`, a response that is not OK becomes its `ErrorBody`.
+pub async fn send(request: Request) -> Result<Response> {
+    let resp = request.send().await.map_err(|e| anyhow!("{}", e))?;
+    if resp.ok() {
+        return Ok(resp);
+    }
+    let status = resp.status();
+    match resp.json::<ErrorBody>().await {
+        Ok(e) => Err(e.into()),
+        Err(_) => Err(anyhow!("Server answered {}", status)),
+    }
+}
+
+/// GET `url` and parse the JSON it answers.
+pub async fn get<T: DeserializeOwned>(url: &str) -> Result<T> {
+    send(Request::get(url))
+        .await?
+        .json()
+        .await
+        .map_err(|e| anyhow!("{}", e))
+}
@@ -1,8 +1,15 @@
+use crate::api;
+use crate::components::cameras::*;
+use crate::components::gallery::*;
 use crate::components::new_tag::*;
+use crate::components::profile::*;
+use crate::components::stats::*;
 use crate::components::tagging::*;
-use anyhow::{anyhow, Result};
-use reqwasm::http::*;
+use crate::eventbus::tags::{EventBus, Msg as TagsMsg};
+use anyhow::{Error, Result};
+use jian_ai_api::names::{self, Names};
 use yew::prelude::*;
+use yew_agent::{Bridge, Bridged};
 use yew_router::prelude::*;
 
 pub enum RemoteValue<T> {
@@ -21,15 +28,32 @@ impl<T> RemoteValue<T> {
     }
 }
 
-type Tags = RemoteValue<Vec<String>>;
+/// A save to the server, `Done` with the error if it failed.
+pub enum RemoteWrite {
+    NotStartedYet,
+    Doing,
+    Done(Option<Error>),
+}
+
+pub fn persist_status(persist: &RemoteWrite) -> Html {
+    match persist {
+        RemoteWrite::Doing => html! {<p>{"正在保存……"}</p>},
+        RemoteWrite::Done(None) => html! {<p>{"保存成功。"}</p>},
+        RemoteWrite::Done(Some(e)) => html! {<p>{format!("保存失败。{}", e)}</p>},
+        RemoteWrite::NotStartedYet => html! {},
+    }
+}
+
+type Tags = RemoteValue<Names>;
 
 pub struct BasePage {
     tags: Tags,
+    _tags_bus: Box<dyn Bridge<EventBus>>,
 }
 
 pub enum Msg {
     GetTags,
-    GetTagsResult(Result<Vec<String>>),
+    GetTagsResult(Result<Names>),
 }
 
 impl Component for BasePage {
@@ -40,6 +64,10 @@ impl Component for BasePage {
         ctx.link().send_message(Msg::GetTags);
         Self {
             tags: RemoteValue::NotStartedYet,
+            _tags_bus: EventBus::bridge(ctx.link().batch_callback(|msg| match msg {
+                TagsMsg::Reload => Some(Msg::GetTags),
+                TagsMsg::Tags(_) => None,
+            })),
         }
     }
 
@@ -48,16 +76,7 @@ impl Component for BasePage {
             Msg::GetTags => {
                 self.tags = RemoteValue::Doing;
                 ctx.link().send_future(async {
-                    match Request::get("http://localhost:8000/apis/names")
-                        .send()
-                        .await
-                    {
-                        Ok(resp) => match resp.json().await {
-                            Ok(ts) => Msg::GetTagsResult(Ok(ts)),
-                            Err(e) => Msg::GetTagsResult(Err(anyhow!("{}", e))),
-                        },
-                        Err(e) => Msg::GetTagsResult(Err(anyhow!("{}", e))),
-                    }
+                    Msg::GetTagsResult(api::get(&api::url(&names::names())).await)
                 })
             }
             Msg::GetTagsResult(x) => self.tags = RemoteValue::Done(x),
@@ -109,6 +128,14 @@ pub enum Route {
     Tagging,
     #[at("/new_tag")]
     NewTag,
+    #[at("/stats")]
+    Stats,
+    #[at("/cameras")]
+    Cameras,
+    #[at("/gallery")]
+    Gallery,
+    #[at("/profile/:name")]
+    Profile { name: String },
 }
 
 fn switch(routes: &Route) -> Html {
@@ -116,6 +143,10 @@ fn switch(routes: &Route) -> Html {
         Route::NotFound => html! { <h1>{"404"}</h1> },
         Route::Tagging => html! { <Tagging /> },
         Route::NewTag => html! { <NewTag /> },
+        Route::Stats => html! { <Stats /> },
+        Route::Cameras => html! { <Cameras /> },
+        Route::Gallery => html! { <Gallery /> },
+        Route::Profile { name } => html! { <NameProfile name={name.clone()} /> },
     }
 }
 
@@ -136,6 +167,27 @@ fn navigator() -> Html {
             } else {
                 "nav-link"
             }
-        })} to={Route::NewTag}>{ "新名称" }</Link<Route>></li>
+        })} to={Route::NewTag}>{ "名称管理" }</Link<Route>></li>
+        <li class="nav-item"><Link<Route> classes={classes!({
+            if curr_r == Some(Route::Stats) {
+                "nav-link active"
+            } else {
+                "nav-link"
+            }
+        })} to={Route::Stats}>{ "统计" }</Link<Route>></li>
+        <li class="nav-item"><Link<Route> classes={classes!({
+            if curr_r == Some(Route::Cameras) {
+                "nav-link active"
+            } else {
+                "nav-link"
+            }
+        })} to={Route::Cameras}>{ "摄像头" }</Link<Route>></li>
+        <li class="nav-item"><Link<Route> classes={classes!({
+            if curr_r == Some(Route::Gallery) {
+                "nav-link active"
+            } else {
+                "nav-link"
+            }
+        })} to={Route::Gallery}>{ "图库" }</Link<Route>></li>
     </ul> }
 }
@@ -0,0 +1,219 @@
+use crate::api;
+use crate::components::base_page::{persist_status, RemoteValue, RemoteWrite};
+use anyhow::Result;
+use jian_ai_api::cameras::{self, NewCamera, Overview};
+use jian_ai_api::photos;
+use reqwasm::http::*;
+use web_sys::HtmlInputElement;
+use yew::events::Event;
+use yew::prelude::*;
+use yew::TargetCast;
+
+async fn post(url: String) -> Result<()> {
+    api::send(Request::post(&url)).await.map(|_| ())
+}
+
+/// Empty value means clearing, which is done by leaving the parameter out.
+fn non_empty(value: &str) -> Option<&str> {
+    Some(value.trim()).filter(|x| !x.is_empty())
+}
+
+pub struct Cameras {
+    cameras: RemoteValue<Vec<Overview>>,
+    new_camera: String,
+    persist_camera: RemoteWrite,
+}
+
+pub enum Msg {
+    GetCameras,
+    GetCamerasResult(Result<Vec<Overview>>),
+    UINewCameraValueState(String),
+    SaveCamera,
+    SaveCameraResult(Result<()>),
+}
+
+impl Component for Cameras {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetCameras);
+        Self {
+            cameras: RemoteValue::NotStartedYet,
+            new_camera: "".to_string(),
+            persist_camera: RemoteWrite::NotStartedYet,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetCameras => {
+                self.cameras = RemoteValue::Doing;
+                ctx.link().send_future(async {
+                    Msg::GetCamerasResult(api::get(&api::url(&cameras::overview())).await)
+                })
+            }
+            Msg::GetCamerasResult(x) => self.cameras = RemoteValue::Done(x),
+            Msg::UINewCameraValueState(v) => self.new_camera = v,
+            Msg::SaveCamera => {
+                self.persist_camera = RemoteWrite::Doing;
+                let id = self.new_camera.clone();
+                ctx.link().send_future(async move {
+                    Msg::SaveCameraResult(
+                        api::send(
+                            Request::post(&api::url(&cameras::list()))
+                                .header("Content-Type", "application/json")
+                                .body(
+                                    serde_json::to_string(&NewCamera {
+                                        id,
+                                        ..Default::default()
+                                    })
+                                    .unwrap_or_default(),
+                                ),
+                        )
+                        .await
+                        .map(|_| ()),
+                    )
+                });
+            }
+            Msg::SaveCameraResult(r) => {
+                if r.is_ok() {
+                    self.new_camera = "".to_string();
+                    ctx.link().send_message(Msg::GetCameras);
+                }
+                self.persist_camera = RemoteWrite::Done(r.err());
+            }
+        };
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {<>
+            <div>
+                {persist_status(&self.persist_camera)}
+                <label for="camera">{"新摄像头："}</label>
+                <input id="camera" type="text" value={self.new_camera.clone()} onchange={ctx.link().callback(|event: Event| {
+                    Msg::UINewCameraValueState(event.target_dyn_into::<HtmlInputElement>().unwrap().value())
+                })} />
+                <button type="button" onclick={ctx.link().callback(|_| Msg::SaveCamera)}>{"Save"}</button>
+            </div>
+            <hr />
+            {match &self.cameras {
+                RemoteValue::Done(Ok(cameras)) => html! {<div class="d-flex flex-wrap gap-3">
+                    {cameras.iter().map(|camera| html!{
+                        <CameraCard camera={camera.clone()} on_changed={ctx.link().callback(|_| Msg::GetCameras)} />
+                    }).collect::<Html>()}
+                </div>},
+                RemoteValue::Done(Err(e)) => html! {<h1>{format!("获取摄像头失败 {}", e)}</h1>},
+                RemoteValue::Doing => html! {<h1>{"正在获取摄像头……"}</h1>},
+                RemoteValue::NotStartedYet => html! {},
+            }}
+        </>}
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct CameraCardProps {
+    pub camera: Overview,
+    /// Fired after the camera is saved, so the list can be refreshed.
+    pub on_changed: Callback<()>,
+}
+
+pub struct CameraCard {
+    display_name: String,
+    location: String,
+    persist: RemoteWrite,
+}
+
+pub enum CardMsg {
+    UIDisplayNameValueState(String),
+    UILocationValueState(String),
+    Rename,
+    Relocate,
+    Retire(bool),
+    SaveResult(Result<()>),
+}
+
+impl Component for CameraCard {
+    type Message = CardMsg;
+    type Properties = CameraCardProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let camera = &ctx.props().camera.camera;
+        Self {
+            display_name: camera.display_name.clone().unwrap_or_default(),
+            location: camera.location.clone().unwrap_or_default(),
+            persist: RemoteWrite::NotStartedYet,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        let id = &ctx.props().camera.camera.id;
+        let url = match msg {
+            CardMsg::UIDisplayNameValueState(v) => {
+                self.display_name = v;
+                return true;
+            }
+            CardMsg::UILocationValueState(v) => {
+                self.location = v;
+                return true;
+            }
+            CardMsg::SaveResult(r) => {
+                if r.is_ok() {
+                    ctx.props().on_changed.emit(());
+                }
+                self.persist = RemoteWrite::Done(r.err());
+                return true;
+            }
+            CardMsg::Rename => cameras::rename(id, non_empty(&self.display_name)),
+            CardMsg::Relocate => cameras::relocate(id, non_empty(&self.location)),
+            CardMsg::Retire(retired) => cameras::retire(id, retired),
+        };
+        self.persist = RemoteWrite::Doing;
+        ctx.link()
+            .send_future(async move { CardMsg::SaveResult(post(api::url(&url)).await) });
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let overview = &ctx.props().camera;
+        let camera = &overview.camera;
+        let retired = camera.retired;
+        html! {<div class="card" style="width: 20rem;">
+            {if let Some(photo) = &overview.latest_photo {
+                html!{<img src={api::url(&photos::thumbnail(160, photo))} class="card-img-top" alt={photo.clone()} loading="lazy" />}
+            } else {html!{}}}
+            <div class="card-body">
+                <h5 class="card-title">
+                    {camera.display_name.clone().unwrap_or_else(|| camera.id.clone())}
+                    {if retired {html!{<span class="badge bg-secondary ms-2">{"已停用"}</span>}} else {html!{}}}
+                </h5>
+                <ul class="list-unstyled card-text">
+                    <li>{format!("编号：{}", camera.id)}</li>
+                    <li>{format!("位置：{}", camera.location.clone().unwrap_or_default())}</li>
+                    <li>{format!("最后上传：{}", overview.last_upload.map(|x| x.to_string()).unwrap_or_default())}</li>
+                    <li>{format!("食物重量：{} / {}",
+                        overview.food_weight.map(|x| x.to_string()).unwrap_or_default(),
+                        camera.feeder_capacity.map(|x| x.to_string()).unwrap_or_default())}</li>
+                    <li>{format!("照片数：{}", overview.photos)}</li>
+                </ul>
+                {persist_status(&self.persist)}
+                <div class="input-group input-group-sm mb-2">
+                    <input type="text" class="form-control" placeholder="名称" value={self.display_name.clone()} onchange={ctx.link().callback(|event: Event| {
+                        CardMsg::UIDisplayNameValueState(event.target_dyn_into::<HtmlInputElement>().unwrap().value())
+                    })} />
+                    <button type="button" class="btn btn-outline-primary" onclick={ctx.link().callback(|_| CardMsg::Rename)}>{"改名"}</button>
+                </div>
+                <div class="input-group input-group-sm mb-2">
+                    <input type="text" class="form-control" placeholder="位置" value={self.location.clone()} onchange={ctx.link().callback(|event: Event| {
+                        CardMsg::UILocationValueState(event.target_dyn_into::<HtmlInputElement>().unwrap().value())
+                    })} />
+                    <button type="button" class="btn btn-outline-primary" onclick={ctx.link().callback(|_| CardMsg::Relocate)}>{"迁移"}</button>
+                </div>
+                <button type="button" class="btn btn-sm btn-outline-danger" onclick={ctx.link().callback(move |_| CardMsg::Retire(!retired))}>
+                    {if retired {"启用"} else {"停用"}}
+                </button>
+            </div>
+        </div>}
+    }
+}
@@ -0,0 +1,403 @@
+use crate::api;
+use crate::components::base_page::{persist_status, RemoteValue, RemoteWrite};
+use anyhow::Result;
+use chrono::NaiveDate;
+use jian_ai_api::cameras::{self, Camera};
+use jian_ai_api::photos::{self, Filter, Order, Page, Photo};
+use reqwasm::http::*;
+use web_sys::{Element, HtmlInputElement, HtmlSelectElement};
+use yew::events::Event;
+use yew::prelude::*;
+use yew::TargetCast;
+
+/// Pixels a thumbnail takes in the grid, both ways.
+const CELL: f64 = 170.0;
+/// Rows rendered above and below the ones in view, and loaded ahead of them.
+const OVERSCAN: usize = 3;
+const PAGE: u32 = 200;
+
+#[derive(Clone, Copy)]
+pub enum Field {
+    Camera,
+    Name,
+    Named,
+    Since,
+    Until,
+    Order,
+}
+
+/// Of the scrolling grid, in pixels.
+#[derive(Default)]
+struct Viewport {
+    top: f64,
+    width: f64,
+    height: f64,
+}
+
+pub struct Gallery {
+    filter: Filter,
+    order: Order,
+    cameras: RemoteValue<Vec<Camera>>,
+    photos: Vec<Photo>,
+    /// `after` of the page to load next, `None` once the last page is in.
+    paging: RemoteValue<Option<String>>,
+    /// Bumped when the filter changes, pages asked for before are dropped.
+    generation: u32,
+    grid: NodeRef,
+    viewport: Viewport,
+    /// Index in `photos` of the one in the lightbox.
+    selected: Option<usize>,
+    rename_to: String,
+    persist_name: RemoteWrite,
+}
+
+pub enum Msg {
+    GetCameras,
+    GetCamerasResult(Result<Vec<Camera>>),
+    UIFilterValueState(Field, String),
+    Reload,
+    More,
+    GetPageResult(u32, Result<Page>),
+    Scrolled,
+    Select(Option<usize>),
+    UIRenameValueState(String),
+    Rename,
+    Unname,
+    SaveResult(String, Option<String>, Result<()>),
+}
+
+fn none_if_empty(v: String) -> Option<String> {
+    Some(v).filter(|x| !x.is_empty())
+}
+
+impl Gallery {
+    fn fetch(&mut self, ctx: &Context<Self>, after: Option<String>) {
+        self.paging = RemoteValue::Doing;
+        let generation = self.generation;
+        let url = api::url(&photos::list(
+            &self.filter,
+            self.order,
+            after.as_deref(),
+            Some(PAGE),
+        ));
+        ctx.link()
+            .send_future(async move { Msg::GetPageResult(generation, api::get(&url).await) });
+    }
+
+    fn measure(&mut self) {
+        if let Some(grid) = self.grid.cast::<Element>() {
+            self.viewport = Viewport {
+                top: grid.scroll_top() as f64,
+                width: grid.client_width() as f64,
+                height: grid.client_height() as f64,
+            };
+        }
+    }
+
+    fn columns(&self) -> usize {
+        ((self.viewport.width / CELL) as usize).max(1)
+    }
+
+    fn rows(&self) -> usize {
+        self.photos.len().div_ceil(self.columns())
+    }
+
+    /// Rows of the grid to render, end excluded.
+    fn visible_rows(&self) -> (usize, usize) {
+        let first = (self.viewport.top / CELL) as usize;
+        let last = ((self.viewport.top + self.viewport.height) / CELL).ceil() as usize;
+        (
+            first.saturating_sub(OVERSCAN),
+            (last + OVERSCAN).min(self.rows()),
+        )
+    }
+
+    /// Whether the rows in view get close to the end of what is loaded.
+    fn wants_more(&self) -> bool {
+        let last = ((self.viewport.top + self.viewport.height) / CELL).ceil() as usize;
+        matches!(self.paging, RemoteValue::Done(Ok(Some(_)))) && last + OVERSCAN >= self.rows()
+    }
+
+    fn save(&mut self, ctx: &Context<Self>, name: Option<String>) {
+        let photo = match self.selected.and_then(|i| self.photos.get(i)) {
+            Some(x) => x.filename.clone(),
+            None => return,
+        };
+        self.persist_name = RemoteWrite::Doing;
+        ctx.link().send_future(async move {
+            let url = match &name {
+                Some(name) => photos::name_image(&photo, name),
+                None => photos::unname_image(&photo),
+            };
+            let r = api::send(Request::post(&api::url(&url))).await.map(|_| ());
+            Msg::SaveResult(photo, name, r)
+        });
+    }
+
+    fn view_filter(&self, ctx: &Context<Self>, names: &[String]) -> Html {
+        let select = |field: Field| {
+            ctx.link().callback(move |event: Event| {
+                Msg::UIFilterValueState(
+                    field,
+                    event
+                        .target_dyn_into::<HtmlSelectElement>()
+                        .unwrap()
+                        .value(),
+                )
+            })
+        };
+        let input = |field: Field| {
+            ctx.link().callback(move |event: Event| {
+                Msg::UIFilterValueState(
+                    field,
+                    event.target_dyn_into::<HtmlInputElement>().unwrap().value(),
+                )
+            })
+        };
+        let camera_id = self.filter.camera_id.clone().unwrap_or_default();
+        let name = self.filter.name.clone().unwrap_or_default();
+        html! {<form class="row g-2 mb-2">
+            <div class="col-auto">
+                <select class="form-select" onchange={select(Field::Camera)}>
+                    <option value="" selected={camera_id.is_empty()}>{"所有摄像头"}</option>
+                    {match &self.cameras {
+                        RemoteValue::Done(Ok(cameras)) => cameras.iter().map(|c| html!{
+                            <option value={c.id.clone()} selected={c.id == camera_id}>{c.display_name.clone().unwrap_or_else(|| c.id.clone())}</option>
+                        }).collect::<Html>(),
+                        _ => html!{},
+                    }}
+                </select>
+            </div>
+            <div class="col-auto">
+                <select class="form-select" onchange={select(Field::Name)}>
+                    <option value="" selected={name.is_empty()}>{"所有名称"}</option>
+                    {names.iter().map(|n| html!{
+                        <option value={n.clone()} selected={*n == name}>{n}</option>
+                    }).collect::<Html>()}
+                </select>
+            </div>
+            <div class="col-auto">
+                <select class="form-select" onchange={select(Field::Named)}>
+                    <option value="" selected={self.filter.named.is_none()}>{"全部"}</option>
+                    <option value="true" selected={self.filter.named == Some(true)}>{"已命名"}</option>
+                    <option value="false" selected={self.filter.named == Some(false)}>{"未命名"}</option>
+                </select>
+            </div>
+            <div class="col-auto">
+                <input class="form-control" type="date" title="从" value={self.filter.since.map(|x| x.date().to_string()).unwrap_or_default()} onchange={input(Field::Since)} />
+            </div>
+            <div class="col-auto">
+                <input class="form-control" type="date" title="到" value={self.filter.until.and_then(|x| x.date().pred_opt()).map(|x| x.to_string()).unwrap_or_default()} onchange={input(Field::Until)} />
+            </div>
+            <div class="col-auto">
+                <select class="form-select" onchange={select(Field::Order)}>
+                    <option value="newest" selected={self.order == Order::NewestFirst}>{"最新在前"}</option>
+                    <option value="oldest" selected={self.order == Order::OldestFirst}>{"最早在前"}</option>
+                </select>
+            </div>
+        </form>}
+    }
+
+    fn view_lightbox(
+        &self,
+        ctx: &Context<Self>,
+        i: usize,
+        photo: &Photo,
+        names: &[String],
+    ) -> Html {
+        let row = |label: &str, value: String| {
+            html! {<><dt class="col-sm-4">{label}</dt><dd class="col-sm-8">{value}</dd></>}
+        };
+        html! {<div class="lightbox" onclick={ctx.link().callback(|_| Msg::Select(None))}>
+            <div class="lightbox-body bg-white rounded-3 p-3" onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}>
+                <img class="img-fluid" src={api::url(&photos::pic(&photo.filename))} alt={photo.filename.clone()} />
+                <dl class="row mt-2">
+                    {row("文件", photo.filename.clone())}
+                    {row("时间", photo.datetime.map(|x| x.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_default())}
+                    {row("摄像头", photo.camera_id.clone())}
+                    {row("食物", photo.food_weight.to_string())}
+                    {row("名称", photo.name.clone().unwrap_or_default())}
+                    {row("置信度", photo.confidence.map(|c| format!("{:.0}%", c * 100.0)).unwrap_or_default())}
+                    {row("尺寸", match (photo.width, photo.height) {
+                        (Some(w), Some(h)) => format!("{}×{}", w, h),
+                        _ => "".to_string(),
+                    })}
+                    {row("来访", photo.visit_id.map(|v| format!("#{}", v)).unwrap_or_default())}
+                </dl>
+                {persist_status(&self.persist_name)}
+                <div class="input-group mb-2">
+                    <select class="form-select" onchange={ctx.link().callback(|event: Event| {
+                        Msg::UIRenameValueState(event.target_dyn_into::<HtmlSelectElement>().unwrap().value())
+                    })}>
+                        <option value="" selected={self.rename_to.is_empty()}>{"命名为……"}</option>
+                        {names.iter().map(|n| html!{
+                            <option value={n.clone()} selected={*n == self.rename_to}>{n}</option>
+                        }).collect::<Html>()}
+                    </select>
+                    <button type="button" class="btn btn-outline-primary" onclick={ctx.link().callback(|_| Msg::Rename)}>{"命名"}</button>
+                    <button type="button" class="btn btn-outline-danger" disabled={photo.name.is_none()} onclick={ctx.link().callback(|_| Msg::Unname)}>{"取消命名"}</button>
+                </div>
+                <div class="d-flex justify-content-between">
+                    <button type="button" class="btn btn-outline-secondary" disabled={i == 0} onclick={ctx.link().callback(move |_| Msg::Select(i.checked_sub(1)))}>{"上一张"}</button>
+                    <button type="button" class="btn btn-outline-secondary" onclick={ctx.link().callback(|_| Msg::Select(None))}>{"关闭"}</button>
+                    <button type="button" class="btn btn-outline-secondary" disabled={i + 1 >= self.photos.len()} onclick={ctx.link().callback(move |_| Msg::Select(Some(i + 1)))}>{"下一张"}</button>
+                </div>
+            </div>
+        </div>}
+    }
+}
+
+impl Component for Gallery {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetCameras);
+        ctx.link().send_message(Msg::Reload);
+        Self {
+            filter: Filter::default(),
+            order: Order::NewestFirst,
+            cameras: RemoteValue::NotStartedYet,
+            photos: vec![],
+            paging: RemoteValue::NotStartedYet,
+            generation: 0,
+            grid: NodeRef::default(),
+            viewport: Viewport::default(),
+            selected: None,
+            rename_to: "".to_string(),
+            persist_name: RemoteWrite::NotStartedYet,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetCameras => {
+                self.cameras = RemoteValue::Doing;
+                ctx.link().send_future(async {
+                    Msg::GetCamerasResult(api::get(&api::url(&cameras::list())).await)
+                })
+            }
+            Msg::GetCamerasResult(x) => self.cameras = RemoteValue::Done(x),
+            Msg::UIFilterValueState(field, v) => {
+                let date = v.parse::<NaiveDate>().ok();
+                match field {
+                    Field::Camera => self.filter.camera_id = none_if_empty(v),
+                    Field::Name => self.filter.name = none_if_empty(v),
+                    Field::Named => self.filter.named = v.parse().ok(),
+                    Field::Since => self.filter.since = date.and_then(|x| x.and_hms_opt(0, 0, 0)),
+                    // The whole day picked is included.
+                    Field::Until => {
+                        self.filter.until = date
+                            .and_then(|x| x.succ_opt())
+                            .and_then(|x| x.and_hms_opt(0, 0, 0))
+                    }
+                    Field::Order => self.order = v.parse().unwrap_or(Order::NewestFirst),
+                }
+                ctx.link().send_message(Msg::Reload);
+            }
+            Msg::Reload => {
+                self.generation += 1;
+                self.photos.clear();
+                self.selected = None;
+                if let Some(grid) = self.grid.cast::<Element>() {
+                    grid.set_scroll_top(0);
+                }
+                self.fetch(ctx, None);
+            }
+            Msg::More => {
+                if let RemoteValue::Done(Ok(Some(after))) = &self.paging {
+                    let after = after.clone();
+                    self.fetch(ctx, Some(after));
+                }
+            }
+            Msg::GetPageResult(generation, x) => {
+                // Drop pages of a filter that is no longer shown.
+                if generation != self.generation {
+                    return false;
+                }
+                self.paging = RemoteValue::Done(x.map(|page| {
+                    self.photos.extend(page.photos);
+                    page.next
+                }));
+                self.measure();
+                if self.wants_more() {
+                    ctx.link().send_message(Msg::More);
+                }
+            }
+            Msg::Scrolled => {
+                self.measure();
+                if self.wants_more() {
+                    ctx.link().send_message(Msg::More);
+                }
+            }
+            Msg::Select(i) => {
+                self.selected = i.filter(|i| *i < self.photos.len());
+                self.rename_to = "".to_string();
+                self.persist_name = RemoteWrite::NotStartedYet;
+            }
+            Msg::UIRenameValueState(v) => self.rename_to = v,
+            Msg::Rename => {
+                if self.rename_to.is_empty() {
+                    return false;
+                }
+                self.save(ctx, Some(self.rename_to.clone()));
+            }
+            Msg::Unname => self.save(ctx, None),
+            Msg::SaveResult(filename, name, r) => {
+                if r.is_ok() {
+                    if let Some(photo) = self.photos.iter_mut().find(|p| p.filename == filename) {
+                        photo.name = name;
+                        photo.confidence = None;
+                    }
+                }
+                self.persist_name = RemoteWrite::Done(r.err());
+            }
+        };
+        true
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            ctx.link().send_message(Msg::Scrolled);
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let (names, _) = ctx
+            .link()
+            .context::<Vec<String>>(Callback::noop())
+            .expect("Context tags is not set");
+        let columns = self.columns();
+        let (first_row, last_row) = self.visible_rows();
+        html! {<div class="container-fluid">
+            {self.view_filter(ctx, &names)}
+            {match &self.paging {
+                RemoteValue::Done(Err(e)) => html! {<p>{format!("获取照片失败 {}", e)}</p>},
+                RemoteValue::Doing => html! {<p>{format!("已获取 {} 张照片，正在获取更多……", self.photos.len())}</p>},
+                RemoteValue::Done(Ok(_)) => html! {<p>{format!("{} 张照片", self.photos.len())}</p>},
+                RemoteValue::NotStartedYet => html! {},
+            }}
+            <div class="gallery-grid" ref={self.grid.clone()} onscroll={ctx.link().callback(|_: Event| Msg::Scrolled)}>
+                <div style={format!("position: relative; height: {}px;", self.rows() as f64 * CELL)}>
+                    {(first_row * columns..(last_row * columns).min(self.photos.len())).map(|i| {
+                        let photo = &self.photos[i];
+                        let style = format!(
+                            "top: {}px; left: {}px; width: {}px; height: {}px;",
+                            (i / columns) as f64 * CELL,
+                            (i % columns) as f64 * CELL,
+                            CELL,
+                            CELL
+                        );
+                        html! {<div key={photo.filename.clone()} class="gallery-cell" style={style} onclick={ctx.link().callback(move |_| Msg::Select(Some(i)))}>
+                            <img src={api::url(&photos::thumbnail(160, &photo.filename))} alt={photo.filename.clone()} title={photo.name.clone().unwrap_or_default()} loading="lazy" />
+                        </div>}
+                    }).collect::<Html>()}
+                </div>
+            </div>
+            {match self.selected.and_then(|i| self.photos.get(i).map(|p| (i, p))) {
+                Some((i, photo)) => self.view_lightbox(ctx, i, photo, &names),
+                None => html! {},
+            }}
+        </div>}
+    }
+}
@@ -1,3 +1,7 @@
 pub mod base_page;
+pub mod cameras;
+pub mod gallery;
 pub mod new_tag;
+pub mod profile;
+pub mod stats;
 pub mod tagging;
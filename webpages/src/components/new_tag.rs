@@ -1,93 +1,106 @@
-use anyhow::{anyhow, Error, Result};
-use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use crate::api;
+use crate::components::base_page::{persist_status, RemoteValue, RemoteWrite, Route};
+use crate::eventbus::tags::{EventBus, Msg as TagsMsg};
+use anyhow::{anyhow, Result};
+use jian_ai_api::names::{self, NameCount, NewName};
 use reqwasm::http::*;
-use web_sys::HtmlInputElement;
-use yew::events::Event;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::events::{Event, InputEvent};
 use yew::prelude::*;
 use yew::TargetCast;
-
-/// https://url.spec.whatwg.org/#fragment-percent-encode-set
-const FRAGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'<').add(b'>').add(b'`');
-
-// need tag management
-
-enum RemoteWrite {
-    NotStartedYet,
-    Doing,
-    Done(Option<Error>),
-}
+use yew_agent::{Dispatched, Dispatcher};
+use yew_router::prelude::*;
 
 pub struct NewTag {
     new_tag: String,
-    persist_tags: RemoteWrite,
+    persist_tags: RemoteValue<Vec<NewName>>,
+    counts: RemoteValue<Vec<NameCount>>,
+    search: String,
+    tags_bus: Dispatcher<EventBus>,
 }
 
 pub enum Msg {
     SaveTag,
-    SaveTagsResult(Result<()>),
+    SaveTagsResult(Result<Vec<NewName>>),
     UINewTagValueState(String),
+    GetCounts,
+    GetCountsResult(Result<Vec<NameCount>>),
+    UISearchValueState(String),
+    Changed,
 }
 
 impl Component for NewTag {
     type Message = Msg;
     type Properties = ();
 
-    fn create(_ctx: &Context<Self>) -> Self {
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetCounts);
         Self {
             new_tag: "".to_string(),
-            persist_tags: RemoteWrite::NotStartedYet,
+            persist_tags: RemoteValue::NotStartedYet,
+            counts: RemoteValue::NotStartedYet,
+            search: "".to_string(),
+            tags_bus: EventBus::dispatcher(),
         }
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::SaveTag => {
-                self.persist_tags = RemoteWrite::Doing;
+                self.persist_tags = RemoteValue::Doing;
                 let tag_to_save = self.new_tag.clone();
                 ctx.link().send_future(async move {
-                    Msg::SaveTagsResult(
-                        Request::post(&format!(
-                            "http://localhost:8000/apis/new_names?names={}",
-                            utf8_percent_encode(&tag_to_save, FRAGMENT)
-                        ))
-                        .send()
-                        .await
-                        .map_err(|e| anyhow!("{}", e))
-                        .and_then(|x| {
-                            if x.ok() {
-                                Ok(())
-                            } else {
-                                Err(anyhow!("Saving tag response is not OK: {}", x.status()))
-                            }
-                        }),
-                    )
+                    let request = Request::post(&api::url(&names::new_names(&tag_to_save)));
+                    Msg::SaveTagsResult(match api::send(request).await {
+                        Ok(resp) => resp.json().await.map_err(|e| anyhow!("{}", e)),
+                        Err(e) => Err(e),
+                    })
                 });
             }
             Msg::SaveTagsResult(r) => {
-                self.persist_tags = RemoteWrite::Done(r.err());
+                if let Ok(new_names) = &r {
+                    if new_names.iter().all(|n| n.error.is_none()) {
+                        self.new_tag = "".to_string();
+                    }
+                    ctx.link().send_message(Msg::Changed);
+                }
+                self.persist_tags = RemoteValue::Done(r);
             }
             Msg::UINewTagValueState(v) => {
                 self.new_tag = v;
             }
+            Msg::GetCounts => {
+                self.counts = RemoteValue::Doing;
+                ctx.link().send_future(async {
+                    Msg::GetCountsResult(api::get(&api::url(&names::counts())).await)
+                });
+            }
+            Msg::GetCountsResult(x) => self.counts = RemoteValue::Done(x),
+            Msg::UISearchValueState(v) => self.search = v,
+            Msg::Changed => {
+                self.tags_bus.send(TagsMsg::Reload);
+                ctx.link().send_message(Msg::GetCounts);
+            }
         };
         true
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
-        let (tags, _) = ctx
-            .link()
-            .context::<Vec<String>>(Callback::noop())
-            .expect("Context tags is not set");
         html! {<>
             <div>
                 {match &self.persist_tags {
-                    RemoteWrite::Doing => {html!{<div class="mask"><h1>{"正在保存……"}</h1></div>}}
-                    RemoteWrite::Done(None) => {html!{<p>{"保存成功。"}</p>}}
-                    RemoteWrite::Done(Some(e)) => {html!{<>
+                    RemoteValue::Doing => {html!{<div class="mask"><h1>{"正在保存……"}</h1></div>}}
+                    RemoteValue::Done(Ok(new_names)) => {html!{<ul>
+                        {new_names.iter().map(|n| match &n.error {
+                            None => html!{<li>{format!("{} 保存成功。", n.name)}</li>},
+                            Some(e) => html!{<li>{format!("{} 保存失败：{}", n.name, e)}</li>},
+                        }).collect::<Html>()}
+                    </ul>}}
+                    RemoteValue::Done(Err(e)) => {html!{<>
                             <p>{"保存失败。"}</p>
                             <p>{e}</p>
                         </>}}
-                    RemoteWrite::NotStartedYet => {html!{}}
+                    RemoteValue::NotStartedYet => {html!{}}
                 }}
                 <label for="tag">{"名称：（多个输入请用“,”分割）"}</label>
                 <input id="tag" type="text" value={self.new_tag.clone()} onchange={ctx.link().callback(move |event: Event| {
@@ -96,11 +109,124 @@ impl Component for NewTag {
                 <button type="button" onclick={ctx.link().callback(move |_| Msg::SaveTag)}>{"Save"}</button>
             </div>
             <hr />
-            <div class="grid">{
-                tags.iter().map(|tag|
-                    html!{<div>{tag}</div>}
-                ).collect::<Html>()
-            }</div>
+            <input type="search" class="form-control mb-2" autocomplete="false" placeholder="过滤……" value={self.search.clone()} oninput={ctx.link().callback(|event: InputEvent| {
+                Msg::UISearchValueState(event.target_unchecked_into::<HtmlInputElement>().value())
+            })} />
+            {match &self.counts {
+                RemoteValue::Done(Ok(counts)) => {
+                    let names = counts.iter().map(|c| c.name.clone()).collect::<Vec<String>>();
+                    html!{<table class="table">
+                        <thead><tr><th>{"名称"}</th><th>{"照片数"}</th><th></th></tr></thead>
+                        <tbody>
+                            {counts.iter().filter(|c| c.name.contains(self.search.trim())).map(|c| html!{
+                                <TagRow key={c.name.clone()} tag={c.clone()} names={names.clone()} on_changed={ctx.link().callback(|_| Msg::Changed)} />
+                            }).collect::<Html>()}
+                        </tbody>
+                    </table>}
+                }
+                RemoteValue::Done(Err(e)) => html! {<h1>{format!("获取名称失败 {}", e)}</h1>},
+                RemoteValue::Doing => html! {<h1>{"正在获取名称……"}</h1>},
+                RemoteValue::NotStartedYet => html! {},
+            }}
         </>}
     }
 }
+
+#[derive(Properties, PartialEq)]
+pub struct TagRowProps {
+    pub tag: NameCount,
+    /// All names, to pick what to merge into.
+    pub names: Vec<String>,
+    /// Fired after the name is changed or gone.
+    pub on_changed: Callback<()>,
+}
+
+pub struct TagRow {
+    rename_to: String,
+    merge_into: String,
+    persist: RemoteWrite,
+}
+
+pub enum RowMsg {
+    UIRenameValueState(String),
+    UIMergeValueState(String),
+    Rename,
+    Merge,
+    Delete,
+    SaveResult(Result<()>),
+}
+
+impl Component for TagRow {
+    type Message = RowMsg;
+    type Properties = TagRowProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            rename_to: "".to_string(),
+            merge_into: "".to_string(),
+            persist: RemoteWrite::NotStartedYet,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        let name = ctx.props().tag.name.clone();
+        let request = match msg {
+            RowMsg::UIRenameValueState(v) => {
+                self.rename_to = v;
+                return true;
+            }
+            RowMsg::UIMergeValueState(v) => {
+                self.merge_into = v;
+                return true;
+            }
+            RowMsg::SaveResult(r) => {
+                if r.is_ok() {
+                    ctx.props().on_changed.emit(());
+                }
+                self.persist = RemoteWrite::Done(r.err());
+                return true;
+            }
+            RowMsg::Rename if !self.rename_to.trim().is_empty() => {
+                Request::post(&api::url(&names::rename(&name, self.rename_to.trim())))
+            }
+            RowMsg::Merge if !self.merge_into.is_empty() => {
+                Request::post(&api::url(&names::merge(&name, &self.merge_into)))
+            }
+            RowMsg::Delete => Request::delete(&api::url(&names::remove(&name))),
+            RowMsg::Rename | RowMsg::Merge => return false,
+        };
+        self.persist = RemoteWrite::Doing;
+        ctx.link()
+            .send_future(async move { RowMsg::SaveResult(api::send(request).await.map(|_| ())) });
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let tag = &ctx.props().tag;
+        html! {<tr>
+            <td><Link<Route> to={Route::Profile { name: tag.name.clone() }}>{&tag.name}</Link<Route>></td>
+            <td>{tag.photos}</td>
+            <td>
+                {persist_status(&self.persist)}
+                <div class="input-group input-group-sm mb-1">
+                    <input type="text" class="form-control" placeholder="新名称" value={self.rename_to.clone()} onchange={ctx.link().callback(|event: Event| {
+                        RowMsg::UIRenameValueState(event.target_dyn_into::<HtmlInputElement>().unwrap().value())
+                    })} />
+                    <button type="button" class="btn btn-outline-primary" onclick={ctx.link().callback(|_| RowMsg::Rename)}>{"改名"}</button>
+                </div>
+                <div class="input-group input-group-sm mb-1">
+                    <select class="form-select" onchange={ctx.link().callback(|event: Event| {
+                        RowMsg::UIMergeValueState(event.target_dyn_into::<HtmlSelectElement>().unwrap().value())
+                    })}>
+                        <option value="" selected={self.merge_into.is_empty()}>{"合并到……"}</option>
+                        {ctx.props().names.iter().filter(|n| **n != tag.name).map(|n| html!{
+                            <option value={n.clone()} selected={*n == self.merge_into}>{n}</option>
+                        }).collect::<Html>()}
+                    </select>
+                    <button type="button" class="btn btn-outline-primary" onclick={ctx.link().callback(|_| RowMsg::Merge)}>{"合并"}</button>
+                </div>
+                <button type="button" class="btn btn-sm btn-outline-danger" disabled={tag.photos > 0} onclick={ctx.link().callback(|_| RowMsg::Delete)}>{"删除"}</button>
+            </td>
+        </tr>}
+    }
+}
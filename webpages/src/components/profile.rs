@@ -0,0 +1,207 @@
+use crate::api;
+use crate::components::base_page::{persist_status, RemoteValue, RemoteWrite};
+use anyhow::{anyhow, Result};
+use jian_ai_api::names::{self, Profile, ProfileChanges};
+use percent_encoding::percent_decode_str;
+use reqwasm::http::*;
+use web_sys::{HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
+use yew::events::Event;
+use yew::prelude::*;
+use yew::TargetCast;
+
+#[derive(Clone, Copy)]
+pub enum Field {
+    Headshot,
+    Aliases,
+    Sex,
+    BirthDate,
+    Neutered,
+    Description,
+    Notes,
+    Status,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct NameProfileProps {
+    pub name: String,
+}
+
+pub struct NameProfile {
+    profile: RemoteValue<Profile>,
+    persist_profile: RemoteWrite,
+}
+
+pub enum Msg {
+    GetProfile,
+    GetProfileResult(Result<Profile>),
+    UIFieldValueState(Field, String),
+    Save,
+    SaveResult(Result<()>),
+}
+
+fn none_if_empty(v: String) -> Option<String> {
+    if v.trim().is_empty() {
+        None
+    } else {
+        Some(v.trim().to_string())
+    }
+}
+
+impl NameProfile {
+    fn url(ctx: &Context<Self>) -> String {
+        let name = percent_decode_str(&ctx.props().name).decode_utf8_lossy();
+        api::url(&names::profile(&name))
+    }
+}
+
+impl Component for NameProfile {
+    type Message = Msg;
+    type Properties = NameProfileProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetProfile);
+        Self {
+            profile: RemoteValue::NotStartedYet,
+            persist_profile: RemoteWrite::NotStartedYet,
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>) -> bool {
+        ctx.link().send_message(Msg::GetProfile);
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetProfile => {
+                self.profile = RemoteValue::Doing;
+                self.persist_profile = RemoteWrite::NotStartedYet;
+                let url = Self::url(ctx);
+                ctx.link()
+                    .send_future(async move { Msg::GetProfileResult(api::get(&url).await) })
+            }
+            Msg::GetProfileResult(x) => self.profile = RemoteValue::Done(x),
+            Msg::UIFieldValueState(field, v) => self.profile.update(|p| match field {
+                Field::Headshot => p.headshot = none_if_empty(v),
+                Field::Aliases => {
+                    p.aliases = v
+                        .split(',')
+                        .filter_map(|a| none_if_empty(a.to_string()))
+                        .collect()
+                }
+                Field::Sex => p.sex = none_if_empty(v),
+                Field::BirthDate => p.birth_date = v.parse().ok(),
+                Field::Neutered => p.neutered = v.parse().ok(),
+                Field::Description => p.description = none_if_empty(v),
+                Field::Notes => p.notes = none_if_empty(v),
+                Field::Status => p.status = v,
+            }),
+            Msg::Save => {
+                if let RemoteValue::Done(Ok(profile)) = &self.profile {
+                    self.persist_profile = RemoteWrite::Doing;
+                    let url = Self::url(ctx);
+                    let body = serde_json::to_string(&ProfileChanges::from(profile));
+                    ctx.link().send_future(async move {
+                        Msg::SaveResult(match body {
+                            Ok(body) => api::send(
+                                Request::put(&url)
+                                    .header("Content-Type", "application/json")
+                                    .body(body),
+                            )
+                            .await
+                            .map(|_| ()),
+                            Err(e) => Err(anyhow!("{}", e)),
+                        })
+                    });
+                }
+            }
+            Msg::SaveResult(r) => self.persist_profile = RemoteWrite::Done(r.err()),
+        };
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let name = percent_decode_str(&ctx.props().name)
+            .decode_utf8_lossy()
+            .to_string();
+        let input = |field: Field| {
+            ctx.link().callback(move |event: Event| {
+                Msg::UIFieldValueState(
+                    field,
+                    event.target_dyn_into::<HtmlInputElement>().unwrap().value(),
+                )
+            })
+        };
+        let select = |field: Field| {
+            ctx.link().callback(move |event: Event| {
+                Msg::UIFieldValueState(
+                    field,
+                    event
+                        .target_dyn_into::<HtmlSelectElement>()
+                        .unwrap()
+                        .value(),
+                )
+            })
+        };
+        let textarea = |field: Field| {
+            ctx.link().callback(move |event: Event| {
+                Msg::UIFieldValueState(
+                    field,
+                    event
+                        .target_dyn_into::<HtmlTextAreaElement>()
+                        .unwrap()
+                        .value(),
+                )
+            })
+        };
+        match &self.profile {
+            RemoteValue::Done(Ok(p)) => html! {<div class="container">
+                <h2>{&name}</h2>
+                {persist_status(&self.persist_profile)}
+                <div class="row">
+                    <div class="col-md-4">
+                        {if let Some(headshot) = &p.headshot {
+                            html!{<img class="img-fluid rounded" src={api::url(&jian_ai_api::photos::pic(headshot))} alt={headshot.clone()} />}
+                        } else {html!{}}}
+                        <p>{format!("首次出现：{}", p.first_seen.map(|x| x.to_string()).unwrap_or_default())}</p>
+                        <p>{format!("最近出现：{}", p.last_seen.map(|x| x.to_string()).unwrap_or_default())}</p>
+                    </div>
+                    <form class="col-md-8">
+                        <label class="form-label" for="headshot">{"头像（照片文件名）"}</label>
+                        <input class="form-control" id="headshot" type="text" value={p.headshot.clone().unwrap_or_default()} onchange={input(Field::Headshot)} />
+                        <label class="form-label" for="aliases">{"别名：（多个输入请用“,”分割）"}</label>
+                        <input class="form-control" id="aliases" type="text" value={p.aliases.join(",")} onchange={input(Field::Aliases)} />
+                        <label class="form-label" for="sex">{"性别"}</label>
+                        <select class="form-select" id="sex" onchange={select(Field::Sex)}>
+                            <option value="" selected={p.sex.is_none()}>{"未知"}</option>
+                            <option value="male" selected={p.sex.as_deref() == Some("male")}>{"公"}</option>
+                            <option value="female" selected={p.sex.as_deref() == Some("female")}>{"母"}</option>
+                        </select>
+                        <label class="form-label" for="birth_date">{"估计出生日期"}</label>
+                        <input class="form-control" id="birth_date" type="date" value={p.birth_date.map(|x| x.to_string()).unwrap_or_default()} onchange={input(Field::BirthDate)} />
+                        <label class="form-label" for="neutered">{"绝育"}</label>
+                        <select class="form-select" id="neutered" onchange={select(Field::Neutered)}>
+                            <option value="" selected={p.neutered.is_none()}>{"未知"}</option>
+                            <option value="true" selected={p.neutered == Some(true)}>{"已绝育"}</option>
+                            <option value="false" selected={p.neutered == Some(false)}>{"未绝育"}</option>
+                        </select>
+                        <label class="form-label" for="description">{"毛色花纹"}</label>
+                        <textarea class="form-control" id="description" value={p.description.clone().unwrap_or_default()} onchange={textarea(Field::Description)} />
+                        <label class="form-label" for="notes">{"备注"}</label>
+                        <textarea class="form-control" id="notes" value={p.notes.clone().unwrap_or_default()} onchange={textarea(Field::Notes)} />
+                        <label class="form-label" for="status">{"状态"}</label>
+                        <select class="form-select" id="status" onchange={select(Field::Status)}>
+                            <option value="active" selected={p.status == "active"}>{"活跃"}</option>
+                            <option value="deceased" selected={p.status == "deceased"}>{"已故"}</option>
+                            <option value="adopted" selected={p.status == "adopted"}>{"已领养"}</option>
+                        </select>
+                        <button type="button" class="mt-2" onclick={ctx.link().callback(|_| Msg::Save)}>{"Save"}</button>
+                    </form>
+                </div>
+            </div>},
+            RemoteValue::Done(Err(e)) => html! {<h1>{format!("获取档案失败 {}", e)}</h1>},
+            RemoteValue::Doing => html! {<h1>{"正在获取档案……"}</h1>},
+            RemoteValue::NotStartedYet => html! {},
+        }
+    }
+}
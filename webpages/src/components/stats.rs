@@ -0,0 +1,188 @@
+use crate::api;
+use crate::components::base_page::RemoteValue;
+use anyhow::Result;
+use jian_ai_api::stats::{self, Consumption, Count, HourlyActivity, Window};
+use std::collections::BTreeMap;
+use yew::prelude::*;
+
+pub struct Stats {
+    window: Window,
+    visits: RemoteValue<Vec<Count>>,
+    consumption: RemoteValue<Vec<Consumption>>,
+    activities: RemoteValue<Vec<HourlyActivity>>,
+}
+
+pub enum Msg {
+    GetStats(Window),
+    GetVisitsResult(Result<Vec<Count>>),
+    GetConsumptionResult(Result<Vec<Consumption>>),
+    GetActivitiesResult(Result<Vec<HourlyActivity>>),
+}
+
+impl Component for Stats {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetStats(Window::Day));
+        Self {
+            window: Window::Day,
+            visits: RemoteValue::NotStartedYet,
+            consumption: RemoteValue::NotStartedYet,
+            activities: RemoteValue::NotStartedYet,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetStats(window) => {
+                self.window = window;
+                self.visits = RemoteValue::Doing;
+                self.consumption = RemoteValue::Doing;
+                self.activities = RemoteValue::Doing;
+                ctx.link().send_future(async move {
+                    Msg::GetVisitsResult(api::get(&api::url(&stats::visits_by_name(window))).await)
+                });
+                ctx.link().send_future(async move {
+                    Msg::GetConsumptionResult(
+                        api::get(&api::url(&stats::consumption_by_camera(window))).await,
+                    )
+                });
+                ctx.link().send_future(async {
+                    Msg::GetActivitiesResult(api::get(&api::url(&stats::hourly_activity())).await)
+                });
+            }
+            Msg::GetVisitsResult(x) => self.visits = RemoteValue::Done(x),
+            Msg::GetConsumptionResult(x) => self.consumption = RemoteValue::Done(x),
+            Msg::GetActivitiesResult(x) => self.activities = RemoteValue::Done(x),
+        };
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {<div class="container-fluid">
+            <div class="btn-group mb-3" role="group">
+                {[(Window::Day, "日"), (Window::Week, "周"), (Window::Month, "月")].iter().map(|(window, label)| {
+                    let window = *window;
+                    html!{<button type="button"
+                        class={classes!("btn", if self.window == window {"btn-primary"} else {"btn-outline-primary"})}
+                        onclick={ctx.link().callback(move |_| Msg::GetStats(window))}>{label}</button>}
+                }).collect::<Html>()}
+            </div>
+
+            <h4>{"到访次数"}</h4>
+            {match &self.visits {
+                RemoteValue::Done(Ok(visits)) => {
+                    grouped_bar_charts(visits.iter().map(|x| (&x.key, &x.period, x.count)))
+                }
+                other => status(other),
+            }}
+
+            <h4>{"各摄像头食物消耗"}</h4>
+            {match &self.consumption {
+                RemoteValue::Done(Ok(consumption)) => {
+                    grouped_bar_charts(consumption.iter().map(|x| (&x.key, &x.period, x.consumed)))
+                }
+                other => status(other),
+            }}
+
+            <h4>{"活动时段"}</h4>
+            {match &self.activities {
+                RemoteValue::Done(Ok(activities)) => heatmap(activities),
+                other => status(other),
+            }}
+        </div>}
+    }
+}
+
+fn status<T>(value: &RemoteValue<T>) -> Html {
+    match value {
+        RemoteValue::Doing => html! {<p>{"正在获取统计……"}</p>},
+        RemoteValue::Done(Err(e)) => html! {<p>{format!("获取统计失败 {}", e)}</p>},
+        _ => html! {},
+    }
+}
+
+const BAR_WIDTH: i64 = 24;
+const CHART_HEIGHT: i64 = 120;
+const LABEL_HEIGHT: i64 = 60;
+const CELL_SIDE: i64 = 20;
+const ROW_LABEL_WIDTH: i64 = 100;
+
+/// One bar chart per key, bars are the periods.
+fn grouped_bar_charts<'a, I>(values: I) -> Html
+where
+    I: Iterator<Item = (&'a String, &'a String, i64)>,
+{
+    let mut groups: BTreeMap<&String, Vec<(&String, i64)>> = BTreeMap::new();
+    for (key, period, value) in values {
+        groups.entry(key).or_default().push((period, value));
+    }
+    if groups.is_empty() {
+        return html! {<p>{"暂无数据"}</p>};
+    }
+    groups
+        .into_iter()
+        .map(|(key, bars)| {
+            html! {<div class="mb-3" style="overflow-x: auto;">
+                <h6>{key}</h6>
+                {bar_chart(&bars)}
+            </div>}
+        })
+        .collect::<Html>()
+}
+
+fn bar_chart(bars: &[(&String, i64)]) -> Html {
+    let max = bars.iter().map(|(_, v)| *v).max().unwrap_or(0).max(1);
+    let width = BAR_WIDTH * bars.len() as i64;
+    html! {<svg width={width.to_string()} height={(CHART_HEIGHT + LABEL_HEIGHT).to_string()}>
+        {bars.iter().enumerate().map(|(i, (label, value))| {
+            let x = i as i64 * BAR_WIDTH;
+            let height = value * CHART_HEIGHT / max;
+            html!{<g>
+                <rect x={(x + 2).to_string()} y={(CHART_HEIGHT - height).to_string()}
+                    width={(BAR_WIDTH - 4).to_string()} height={height.to_string()} fill="#0d6efd">
+                    <title>{format!("{} {}", label, value)}</title>
+                </rect>
+                <text x={(x + 4).to_string()} y={(CHART_HEIGHT + 10).to_string()} font-size="10"
+                    transform={format!("rotate(60 {} {})", x + 4, CHART_HEIGHT + 10)}>{label}</text>
+            </g>}
+        }).collect::<Html>()}
+    </svg>}
+}
+
+/// Rows are animals, columns are hours of the day, darker is busier.
+fn heatmap(activities: &[HourlyActivity]) -> Html {
+    if activities.is_empty() {
+        return html! {<p>{"暂无数据"}</p>};
+    }
+    let max = activities
+        .iter()
+        .flat_map(|a| a.visits.iter())
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let width = ROW_LABEL_WIDTH + CELL_SIDE * 24;
+    let height = CELL_SIDE * (activities.len() as i64 + 1);
+    html! {<div style="overflow-x: auto;">
+        <svg width={width.to_string()} height={height.to_string()}>
+            {(0..24).map(|hour| html!{
+                <text x={(ROW_LABEL_WIDTH + hour * CELL_SIDE + 2).to_string()} y={(CELL_SIDE - 6).to_string()} font-size="10">{hour}</text>
+            }).collect::<Html>()}
+            {activities.iter().enumerate().map(|(row, activity)| {
+                let y = (row as i64 + 1) * CELL_SIDE;
+                html!{<g>
+                    <text x="0" y={(y + CELL_SIDE - 6).to_string()} font-size="12">{&activity.name}</text>
+                    {activity.visits.iter().enumerate().map(|(hour, count)| html!{
+                        <rect x={(ROW_LABEL_WIDTH + hour as i64 * CELL_SIDE).to_string()} y={y.to_string()}
+                            width={(CELL_SIDE - 1).to_string()} height={(CELL_SIDE - 1).to_string()}
+                            fill="#198754" fill-opacity={format!("{:.2}", *count as f64 / max as f64)}>
+                            <title>{format!("{} {}:00 {}", activity.name, hour, count)}</title>
+                        </rect>
+                    }).collect::<Html>()}
+                </g>}
+            }).collect::<Html>()}
+        </svg>
+    </div>}
+}
@@ -1,33 +1,29 @@
-use crate::components::base_page::RemoteValue;
-use anyhow::{anyhow, Result};
-use linked_hash_set::LinkedHashSet;
-use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use crate::api;
+use crate::components::base_page::{persist_status, RemoteValue, RemoteWrite};
+use anyhow::Result;
+use jian_ai_api::photos::{self, Identification, Photo};
 use reqwasm::http::*;
 use std::sync::Arc;
 use yew::prelude::*;
 
-type Photos = RemoteValue<LinkedHashSet<String>>;
-
-// Also in new_tag.
-const FRAGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'<').add(b'>').add(b'`');
-enum RemoteWrite {
-    NotStartedYet,
-    Doing,
-    Done(Result<String>),
-}
+type Photos = RemoteValue<Vec<Photo>>;
 
 pub struct Tagging {
     photos: Arc<Photos>,
     current_photo: Option<String>,
     current_name: Option<String>,
+    suggestions: RemoteValue<Vec<Identification>>,
     persist_name: RemoteWrite,
 }
 
 pub enum Msg {
     GetPhotos,
-    GetPhotosResult(Result<Vec<String>>),
+    GetPhotosResult(Result<Vec<Photo>>),
     PhotoClicked(String),
     NameClicked(String),
+    GetSuggestions(String),
+    GetSuggestionsResult(String, Result<Vec<Identification>>),
+    AcceptSuggestion(String),
     Save,
     SaveResult(Result<String>),
     Next,
@@ -43,6 +39,7 @@ impl Component for Tagging {
             photos: Arc::new(RemoteValue::NotStartedYet),
             current_photo: None,
             current_name: None,
+            suggestions: RemoteValue::NotStartedYet,
             persist_name: RemoteWrite::NotStartedYet,
         }
     }
@@ -52,59 +49,58 @@ impl Component for Tagging {
             Msg::GetPhotos => {
                 self.photos = Arc::new(RemoteValue::Doing);
                 ctx.link().send_future(async {
-                    match Request::get("http://localhost:8000/apis/unnamed_images")
-                        .send()
-                        .await
-                    {
-                        Ok(resp) => match resp.json().await {
-                            Ok(ps) => Msg::GetPhotosResult(Ok(ps)),
-                            Err(e) => Msg::GetPhotosResult(Err(anyhow!("{}", e))),
-                        },
-                        Err(e) => Msg::GetPhotosResult(Err(anyhow!("{}", e))),
-                    }
+                    Msg::GetPhotosResult(api::get(&api::url(&photos::unnamed_images())).await)
                 })
             }
             Msg::GetPhotosResult(x) => match x {
                 Ok(v_photos) => {
-                    ctx.link()
-                        .send_message(Msg::PhotoClicked(v_photos[0].clone()));
-                    self.photos = Arc::new(RemoteValue::Done(Ok(v_photos.into_iter().collect())));
+                    if let Some(first) = v_photos.first() {
+                        ctx.link()
+                            .send_message(Msg::PhotoClicked(first.filename.clone()));
+                    }
+                    self.photos = Arc::new(RemoteValue::Done(Ok(v_photos)));
                 }
                 Err(e) => {
                     self.photos = Arc::new(RemoteValue::Done(Err(e)));
                 }
             },
             Msg::PhotoClicked(i) => {
+                ctx.link().send_message(Msg::GetSuggestions(i.clone()));
                 self.current_photo = Some(i);
                 self.current_name = None;
             }
             Msg::NameClicked(n) => {
                 self.current_name = Some(n);
             }
+            Msg::GetSuggestions(photo) => {
+                self.suggestions = RemoteValue::Doing;
+                ctx.link().send_future(async move {
+                    let suggestions =
+                        api::get(&api::url(&photos::suggest_names(&photo, None))).await;
+                    Msg::GetSuggestionsResult(photo, suggestions)
+                })
+            }
+            Msg::GetSuggestionsResult(photo, x) => {
+                // Drop answers for photos that are no longer shown.
+                if self.current_photo == Some(photo) {
+                    self.suggestions = RemoteValue::Done(x);
+                }
+            }
+            Msg::AcceptSuggestion(n) => {
+                self.current_name = Some(n);
+                ctx.link().send_message(Msg::Save);
+            }
             Msg::Save => {
                 self.current_photo.clone().map(|photo| {
                     self.current_name.clone().map(|name| {
                         self.persist_name = RemoteWrite::Doing;
                         ctx.link().send_future(async move {
                             Msg::SaveResult(
-                                Request::post(&format!(
-                                "http://localhost:8000/apis/name_image?photo_filename={}&name={}",
-                                photo,
-                                utf8_percent_encode(&name, FRAGMENT)
-                            ))
-                                .send()
+                                api::send(Request::post(&api::url(&photos::name_image(
+                                    &photo, &name,
+                                ))))
                                 .await
-                                .map_err(|e| anyhow!("{}", e))
-                                .and_then(|x| {
-                                    if x.ok() {
-                                        Ok(photo)
-                                    } else {
-                                        Err(anyhow!(
-                                            "Naming photo response is not OK: {}",
-                                            x.status()
-                                        ))
-                                    }
-                                }),
+                                .map(|_| photo),
                             )
                         });
                     })
@@ -113,12 +109,10 @@ impl Component for Tagging {
             Msg::SaveResult(r) => {
                 let _ = &r.as_ref().map(|saved_photo| {
                     Arc::get_mut(&mut self.photos).map(|photos| {
-                        photos.update(|ps| {
-                            ps.remove(saved_photo);
-                        })
+                        photos.update(|ps| ps.retain(|p| p.filename != *saved_photo))
                     })
                 });
-                self.persist_name = RemoteWrite::Done(r);
+                self.persist_name = RemoteWrite::Done(r.err());
             }
             Msg::Next => {
                 let current_photo = self.current_photo.clone();
@@ -127,11 +121,11 @@ impl Component for Tagging {
                     if let RemoteValue::Done(Ok(photos)) = tmp {
                         let mut iter = photos.iter();
                         while let Some(photo) = iter.next() {
-                            if *photo == *curr_photo {
+                            if photo.filename == curr_photo {
                                 if let Some(next_photo) = iter.next() {
-                                    self.current_photo = Some(next_photo.clone());
-                                } else if let Some(first_photo) = photos.front() {
-                                    self.current_photo = Some(first_photo.clone());
+                                    self.current_photo = Some(next_photo.filename.clone());
+                                } else if let Some(first_photo) = photos.first() {
+                                    self.current_photo = Some(first_photo.filename.clone());
                                 } else {
                                     self.current_photo = None;
                                 }
@@ -142,6 +136,9 @@ impl Component for Tagging {
                         self.current_photo = None;
                     }
                 });
+                if let Some(photo) = self.current_photo.clone() {
+                    ctx.link().send_message(Msg::GetSuggestions(photo));
+                }
             }
         };
         true
@@ -155,8 +152,8 @@ impl Component for Tagging {
                         html!{<>
                             <p class="d-flex align-items-center flex-shrink-0 p-3 link-dark text-decoration-none border-bottom fs-5 fw-semibold">{format!("未命名照片 {}", photos.iter().len())}</p>
                             <div class="list-group list-group-flush border-bottom scrollarea">
-                                {photos.iter().enumerate().map(|(i, photo_)| {
-                                    let photo = photo_.clone();
+                                {photos.iter().map(|record| {
+                                    let photo = record.filename.clone();
                                     let p = photo.clone();
                                     let cls = if self.current_photo == Some(photo.clone()) {
                                         classes!("list-group-item", "list-group-item-action", "py-3", "lh-tight", "d-flex", "w-100", "align-items-center", "justify-content-between", "active")
@@ -169,8 +166,24 @@ impl Component for Tagging {
                                                 Msg::PhotoClicked(p.clone())
                                             })
                                         }>
-                                            <span class="mb-1">{i}</span>
-                                            <img src={format!("http://localhost:8000/pics/{}", photo)} class="mb-1" alt={photo.clone()} loading="lazy" />
+                                            <div class="small me-2">
+                                                <div class="fw-semibold">{record.datetime.map(|x| x.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_default()}</div>
+                                                <div>{format!("摄像头 {}", record.camera_id)}</div>
+                                                <div>{format!("食物 {}", record.food_weight)}</div>
+                                                {if let Some(name) = &record.name {
+                                                    html!{<div>{match record.confidence {
+                                                        Some(c) => format!("{} {:.0}%", name, c * 100.0),
+                                                        None => name.clone(),
+                                                    }}</div>}
+                                                } else {html!{}}}
+                                                {if let (Some(w), Some(h)) = (record.width, record.height) {
+                                                    html!{<div>{format!("{}×{}", w, h)}</div>}
+                                                } else {html!{}}}
+                                                {if let Some(v) = record.visit_id {
+                                                    html!{<div>{format!("来访 #{}", v)}</div>}
+                                                } else {html!{}}}
+                                            </div>
+                                            <img src={api::url(&photos::thumbnail(160, &photo))} class="mb-1" alt={photo.clone()} loading="lazy" />
                                         </div>
                                     }
                                 }).collect::<Html>()}
@@ -191,9 +204,29 @@ impl Component for Tagging {
                     .context::<Vec<String>>(Callback::noop())
                     .expect("Context tags is not set");
                 html! {<div class="tag-layout" style="width: 100%;">
-                    <div style="grid-area: photo; text-align: center;"><img src={format!("http://localhost:8000/pics/{}", &curr_photo)} alt={curr_photo.clone()} /></div>
+                    <div style="grid-area: photo; text-align: center;"><img src={api::url(&photos::pic(&curr_photo))} alt={curr_photo.clone()} /></div>
 
                     <div class="pt-0 mx-0 rounded-3 shadow overflow-hidden" style="grid-area: names;">
+                        {match &self.suggestions {
+                            RemoteValue::Done(Ok(suggestions)) if !suggestions.is_empty() => {
+                                html!{<ul class="list-unstyled mb-0 p-2 bg-light border-bottom">
+                                    {suggestions.iter().map(|suggestion| {
+                                        let name = suggestion.name.clone();
+                                        html!{<li class="dropdown-item d-flex align-items-center gap-2 py-2" onclick={ctx.link().callback(move |_| Msg::AcceptSuggestion(name.clone()))}>
+                                            <span class="badge bg-primary rounded-pill">{format!("{:.0}%", suggestion.confidence * 100.0)}</span>
+                                            {&suggestion.name}
+                                        </li>}
+                                    }).collect::<Html>()}
+                                </ul>}
+                            }
+                            RemoteValue::Done(Err(e)) => {
+                                html! {<p class="p-2 mb-0 border-bottom">{format!("获取建议失败 {}", e)}</p>}
+                            }
+                            RemoteValue::Doing => {
+                                html! {<p class="p-2 mb-0 border-bottom">{"正在获取建议……"}</p>}
+                            }
+                            _ => html!{}
+                        }}
                         <form class="p-2 mb-2 bg-light border-bottom">
                             <input type="search" class="form-control" autocomplete="false" placeholder="过滤……" />
                         </form>
@@ -211,6 +244,7 @@ impl Component for Tagging {
 
                     <div style="grid-area: name;">
                         <label>{if let Some(current_name) = self.current_name.clone() {current_name} else {"".to_string()}}</label>
+                        {persist_status(&self.persist_name)}
                     </div>
 
                     <div style="grid-area: buttons; text-align: center;">
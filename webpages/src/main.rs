@@ -1,4 +1,6 @@
+mod api;
 mod components;
+mod eventbus;
 
 fn main() {
     wasm_logger::init(wasm_logger::Config::default());